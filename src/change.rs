@@ -1,31 +1,441 @@
+//! Changes and where in a spec they happened.
+
+use serde::{Deserialize, Serialize};
 use std::fmt;
+use std::str::FromStr;
+
+use crate::diff::Event;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// How a change affects existing clients, ordered from most to least severe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+#[non_exhaustive]
 pub enum Severity {
+    /// Existing clients can fail.
     Breaking,
+    /// Legal per the spec, but likely to trip up some clients.
+    Warning,
+    /// Additions and relaxations clients can ignore.
     NonBreaking,
+    /// Documentation-only changes: summaries, descriptions, tags, examples.
+    Info,
+    /// Any change under an `x-stability` level other than `stable`; never
+    /// fails the build.
+    Unstable,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Breaking => write!(f, "breaking"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::NonBreaking => write!(f, "non-breaking"),
+            Severity::Info => write!(f, "info"),
+            Severity::Unstable => write!(f, "unstable"),
+        }
+    }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+impl FromStr for Severity {
+    type Err = String;
+
+    /// Parse a severity as displayed, e.g. `non-breaking`.
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "breaking" => Ok(Severity::Breaking),
+            "warning" => Ok(Severity::Warning),
+            "non-breaking" => Ok(Severity::NonBreaking),
+            "info" => Ok(Severity::Info),
+            "unstable" => Ok(Severity::Unstable),
+            other => Err(format!("unknown severity '{other}'")),
+        }
+    }
+}
+
+/// Where a change happened. The document, path, operation, callback,
+/// webhook and component variants are scopes; the others narrow down a
+/// `parent` location to a detail inside it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[non_exhaustive]
 pub enum Location {
+    /// The document as a whole, e.g. its `info` or top-level `servers`.
+    Document,
+    /// A path item, by its path template.
     Path(String),
-    Operation { path: String, method: String },
+    /// An operation, by path template and upper-case HTTP method.
+    Operation {
+        /// Path template, such as `/pets/{petId}`.
+        path: String,
+        /// HTTP method, such as `GET`.
+        method: String,
+    },
+    /// A path item or operation inside one of `parent`'s callbacks.
+    Callback {
+        /// The operation declaring the callback.
+        parent: Box<Location>,
+        /// The callback's name.
+        name: String,
+        /// The path item or operation within the callback, keyed by its
+        /// runtime expression.
+        inner: Box<Location>,
+    },
+    /// A path item or operation under `x-webhooks`.
+    Webhook(Box<Location>),
+    /// A schema under `components/schemas`, diffed once for all its uses.
+    Component(String),
+    /// A parameter of the operation in `parent`, keyed by name and `in`.
+    Parameter {
+        /// The operation.
+        parent: Box<Location>,
+        /// Parameter name.
+        name: String,
+        /// `query`, `header`, `path` or `cookie`.
+        r#in: String,
+    },
+    /// The request body of the operation in `parent`.
+    RequestBody {
+        /// The operation.
+        parent: Box<Location>,
+    },
+    /// A response of the operation in `parent`, by status code, range
+    /// (`4XX`) or `default`.
+    Response {
+        /// The operation.
+        parent: Box<Location>,
+        /// Status code, range or `default`.
+        code: String,
+    },
+    /// A media type of the request body or response in `parent`.
+    MediaType {
+        /// The request body or response.
+        parent: Box<Location>,
+        /// Media type as written in the old spec, or in the new one for
+        /// additions.
+        media: String,
+        /// The new spec's media type serving the old one, when written
+        /// differently; left out of the canonical form and display.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        new_media: Option<String>,
+    },
+    /// A header of the response in `parent`.
+    Header {
+        /// The response.
+        parent: Box<Location>,
+        /// Header name.
+        name: String,
+    },
+    /// A property path inside the schema at `parent`, such as `owner.name`
+    /// or `[].tags[]`, where `[]` steps into array items.
+    Schema {
+        /// The media type, parameter or component holding the schema.
+        parent: Box<Location>,
+        /// Path from the schema root.
+        path: String,
+    },
+    /// A security scheme required by the operation in `parent`.
+    Security {
+        /// The operation.
+        parent: Box<Location>,
+        /// Name of the scheme under `components/securitySchemes`.
+        scheme: String,
+    },
+    /// A server of the document, path item or operation in `parent`.
+    Server {
+        /// The document, path or operation declaring the server.
+        parent: Box<Location>,
+        /// Server URL template.
+        url: String,
+    },
+}
+
+impl Location {
+    /// Parameter `name` in `r#in` of the operation at this location.
+    pub fn parameter(&self, name: &str, r#in: &str) -> Location {
+        Location::Parameter {
+            parent: Box::new(self.clone()),
+            name: name.into(),
+            r#in: r#in.into(),
+        }
+    }
+
+    /// The request body of the operation at this location.
+    pub fn request_body(&self) -> Location {
+        Location::RequestBody {
+            parent: Box::new(self.clone()),
+        }
+    }
+
+    /// Response `code` of the operation at this location.
+    pub fn response(&self, code: &str) -> Location {
+        Location::Response {
+            parent: Box::new(self.clone()),
+            code: code.into(),
+        }
+    }
+
+    /// Media type `media` of the request body or response at this location.
+    pub fn media_type(&self, media: &str) -> Location {
+        Location::MediaType {
+            parent: Box::new(self.clone()),
+            media: media.into(),
+            new_media: None,
+        }
+    }
+
+    /// Header `name` of the response at this location.
+    pub fn header(&self, name: &str) -> Location {
+        Location::Header {
+            parent: Box::new(self.clone()),
+            name: name.into(),
+        }
+    }
+
+    /// Step into property `name` of the schema at this location.
+    pub fn property(&self, name: &str) -> Location {
+        self.schema_path(&format!(".{name}"))
+    }
+
+    /// Step into the array items of the schema at this location.
+    pub fn items(&self) -> Location {
+        self.schema_path("[]")
+    }
+
+    fn schema_path(&self, step: &str) -> Location {
+        match self {
+            Location::Schema { parent, path } => Location::Schema {
+                parent: parent.clone(),
+                path: format!("{path}{step}"),
+            },
+            _ => Location::Schema {
+                parent: Box::new(self.clone()),
+                path: step.strip_prefix('.').unwrap_or(step).into(),
+            },
+        }
+    }
+
+    /// Security scheme `scheme` of the operation at this location.
+    pub fn security(&self, scheme: &str) -> Location {
+        Location::Security {
+            parent: Box::new(self.clone()),
+            scheme: scheme.into(),
+        }
+    }
+
+    /// Server `url` of the document, path or operation at this location.
+    pub fn server(&self, url: &str) -> Location {
+        Location::Server {
+            parent: Box::new(self.clone()),
+            url: url.into(),
+        }
+    }
+
+    /// The location as `/`-separated segments from the scope down, each a
+    /// kind and its keys separated by `:`, for example
+    /// `operation:GET /pets/parameter:query:limit`,
+    /// `component:Pet/schema:owner.name` or `document/server:https://a.io`.
+    /// Unlike `Display`, it is unambiguous, and it is kept stable because
+    /// fingerprints are computed from it.
+    pub fn canonical(&self) -> String {
+        let own = match self {
+            Location::Document => return "document".into(),
+            Location::Path(path) => return format!("path:{path}"),
+            Location::Operation { path, method } => return format!("operation:{method} {path}"),
+            Location::Callback {
+                parent,
+                name,
+                inner,
+            } => {
+                return format!(
+                    "{}/callback:{name}/{}",
+                    parent.canonical(),
+                    inner.canonical()
+                );
+            }
+            Location::Webhook(inner) => return format!("webhook/{}", inner.canonical()),
+            Location::Component(name) => return format!("component:{name}"),
+            Location::Parameter { name, r#in, .. } => format!("parameter:{}:{name}", r#in),
+            Location::RequestBody { .. } => "request-body".into(),
+            Location::Response { code, .. } => format!("response:{code}"),
+            Location::MediaType { media, .. } => format!("media-type:{media}"),
+            Location::Header { name, .. } => format!("header:{name}"),
+            Location::Schema { path, .. } => format!("schema:{path}"),
+            Location::Security { scheme, .. } => format!("security:{scheme}"),
+            Location::Server { url, .. } => format!("server:{url}"),
+        };
+        match self.parent() {
+            Some(parent) => format!("{}/{own}", parent.canonical()),
+            None => own,
+        }
+    }
+
+    /// The location this one is a detail of, if any.
+    pub fn parent(&self) -> Option<&Location> {
+        match self {
+            Location::Parameter { parent, .. }
+            | Location::RequestBody { parent }
+            | Location::Response { parent, .. }
+            | Location::MediaType { parent, .. }
+            | Location::Header { parent, .. }
+            | Location::Schema { parent, .. }
+            | Location::Security { parent, .. }
+            | Location::Server { parent, .. } => Some(parent),
+            _ => None,
+        }
+    }
+
+    /// The document, path, operation, callback, webhook or component this
+    /// location is in.
+    pub fn scope(&self) -> &Location {
+        match self.parent() {
+            Some(parent) => parent.scope(),
+            None => self,
+        }
+    }
+
+    /// Everything below `scope()`, e.g. `response '200' application/json.tags[]`;
+    /// empty for a scope itself.
+    pub fn detail(&self) -> String {
+        let Some(parent) = self.parent() else {
+            return String::new();
+        };
+        let outer = parent.detail();
+        let own = match self {
+            // Property paths continue their media type (or component name)
+            // directly: `application/json.owner`, `Pet[]`.
+            Location::Schema { path, .. } => {
+                let base = match parent {
+                    Location::Component(name) => name,
+                    _ => &outer,
+                };
+                return match (base.is_empty(), path.starts_with('[')) {
+                    (true, _) | (false, true) => format!("{base}{path}"),
+                    (false, false) => format!("{base}.{path}"),
+                };
+            }
+            Location::Parameter { name, r#in, .. } => format!("{} parameter '{name}'", r#in),
+            Location::RequestBody { .. } => "request body".into(),
+            Location::Response { code, .. } => format!("response '{code}'"),
+            Location::MediaType { media, .. } => media.clone(),
+            Location::Header { name, .. } => format!("header '{name}'"),
+            Location::Security { scheme, .. } => format!("security scheme '{scheme}'"),
+            Location::Server { url, .. } => format!("server '{url}'"),
+            _ => unreachable!("scopes have no parent"),
+        };
+        if outer.is_empty() {
+            own
+        } else {
+            format!("{outer} {own}")
+        }
+    }
 }
 
-#[derive(Debug, Clone)]
+/// The steps of a schema property path: `owner.tags[]` gives `owner`,
+/// `tags` and `[]`.
+pub(crate) fn schema_steps(path: &str) -> impl Iterator<Item = &str> {
+    path.split('.')
+        .flat_map(|part| part.split_inclusive("[]"))
+        .flat_map(|part| match part.strip_suffix("[]") {
+            Some(name) => [Some(name), Some("[]")],
+            None => [Some(part), None],
+        })
+        .flatten()
+        .filter(|step| !step.is_empty())
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Location::Document => Ok(()),
+            Location::Path(path) => write!(f, "{path}"),
+            Location::Operation { method, path } => write!(f, "{method} {path}"),
+            Location::Callback {
+                parent,
+                name,
+                inner,
+            } => write!(f, "{parent} -> callback {name} {inner}"),
+            Location::Webhook(inner) => write!(f, "webhook {inner}"),
+            Location::Component(name) => write!(f, "component {name}"),
+            detail => match detail.scope() {
+                Location::Document => write!(f, "{}", detail.detail()),
+                scope => write!(f, "{scope} {}", detail.detail()),
+            },
+        }
+    }
+}
+
+/// A single difference between two specs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[non_exhaustive]
 pub struct Change {
+    /// How the change affects existing clients.
     pub severity: Severity,
+    /// Where the change happened.
     pub location: Location,
+    /// What changed, relative to `location`.
     pub message: String,
+    /// The event the change was classified from, or `None` for findings
+    /// rules report alongside the events.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event: Option<Event>,
+}
+
+impl Change {
+    /// A change at `location` with no event behind it, like the findings
+    /// rules report.
+    pub fn new(severity: Severity, location: Location, message: impl Into<String>) -> Self {
+        Change {
+            severity,
+            location,
+            message: message.into(),
+            event: None,
+        }
+    }
+
+    /// Record the event the change was classified from.
+    pub fn with_event(mut self, event: Event) -> Self {
+        self.event = Some(event);
+        self
+    }
+
+    /// A stable identifier for the change, for deduplication and baselines:
+    /// a 64-bit FNV-1a hash of the location's [canonical
+    /// form](Location::canonical), a newline, and the message of the event
+    /// the change was classified from. Findings of rules, which have no
+    /// event, use their own message.
+    ///
+    /// Severity is left out, and so are the explanations a removal policy
+    /// or a shared component adds to the message, so a change keeps its
+    /// fingerprint however the options judge it.
+    pub fn fingerprint(&self) -> String {
+        let message = match &self.event {
+            Some(event) => event.kind.to_string(),
+            None => self.message.clone(),
+        };
+        // 64-bit FNV-1a, which unlike `DefaultHasher` is the same everywhere.
+        let hash = format!("{}\n{message}", self.location.canonical())
+            .bytes()
+            .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+                (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+            });
+        format!("{hash:016x}")
+    }
+
+    /// The change relative to its scope: `detail: message`, or just the
+    /// message for changes to the scope itself.
+    pub fn describe(&self) -> String {
+        match self.location.detail() {
+            detail if detail.is_empty() => self.message.clone(),
+            detail => format!("{detail}: {}", self.message),
+        }
+    }
 }
 
 impl fmt::Display for Change {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match &self.location {
-            Location::Path(path) => write!(f, "{path} - {}", self.message),
-            Location::Operation { method, path } => {
-                write!(f, "{method} {path} - {}", self.message)
-            }
+        match self.location.scope() {
+            Location::Document => write!(f, "{}", self.describe()),
+            scope => write!(f, "{scope} - {}", self.describe()),
         }
     }
 }
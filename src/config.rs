@@ -0,0 +1,148 @@
+//! Reading the YAML configuration file.
+
+use serde::Deserialize;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::diff::{Rules, ScriptError, ScriptRule};
+
+/// Settings read from a configuration file such as:
+///
+/// ```yaml
+/// rules:
+///   disable: [documentation]
+///   scripts:
+///     - name: parameter-case
+///       path: rules/parameter-case.rhai
+///     - name: no-beta-tag
+///       source: |
+///         if event.kind == "TagAdded" && event.tag == "beta" { "warning" }
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+#[non_exhaustive]
+pub struct Config {
+    /// Which rules classify changes.
+    #[serde(default)]
+    pub rules: RulesConfig,
+}
+
+/// The `rules` section of the configuration.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+#[non_exhaustive]
+pub struct RulesConfig {
+    /// Names of built-in rules to turn off.
+    #[serde(default)]
+    pub disable: Vec<String>,
+    /// Script rules, consulted before the built-in ones in the order listed.
+    #[serde(default)]
+    pub scripts: Vec<ScriptConfig>,
+}
+
+/// A Rhai script rule, given inline or as a file.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+#[non_exhaustive]
+pub struct ScriptConfig {
+    /// Name of the rule, used in reports and to replace a built-in rule of
+    /// the same name.
+    pub name: String,
+    /// Script file, relative to the configuration file.
+    #[serde(default)]
+    pub path: Option<PathBuf>,
+    /// The script itself, instead of `path`.
+    #[serde(default)]
+    pub source: Option<String>,
+}
+
+/// A configuration file that could not be loaded.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ConfigError {
+    /// The configuration file or a script file could not be read.
+    Io(String, std::io::Error),
+    /// The configuration file is not valid.
+    Parse(String, serde_yml::Error),
+    /// A script rule has neither or both of `path` and `source`.
+    Source(String),
+    /// A script rule failed to compile.
+    Script(ScriptError),
+    /// `disable` names a rule that doesn't exist.
+    UnknownRule(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(path, e) => write!(f, "failed to read '{path}': {e}"),
+            ConfigError::Parse(path, e) => write!(f, "invalid config '{path}': {e}"),
+            ConfigError::Source(name) => {
+                write!(f, "rule '{name}' needs exactly one of 'path' or 'source'")
+            }
+            ConfigError::Script(e) => write!(f, "failed to compile {e}"),
+            ConfigError::UnknownRule(name) => write!(f, "cannot disable unknown rule '{name}'"),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConfigError::Io(_, e) => Some(e),
+            ConfigError::Parse(_, e) => Some(e),
+            ConfigError::Script(e) => Some(e),
+            ConfigError::Source(_) | ConfigError::UnknownRule(_) => None,
+        }
+    }
+}
+
+fn read(path: &Path) -> Result<String, ConfigError> {
+    std::fs::read_to_string(path).map_err(|e| ConfigError::Io(path.display().to_string(), e))
+}
+
+impl Config {
+    /// Load a configuration file, reading script files relative to it.
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let mut config: Config = serde_yml::from_str(&read(path)?)
+            .map_err(|e| ConfigError::Parse(path.display().to_string(), e))?;
+        let dir = path.parent().unwrap_or(Path::new(""));
+        for script in &mut config.rules.scripts {
+            match (&script.path, &script.source) {
+                (Some(file), None) => script.source = Some(read(&dir.join(file))?),
+                (None, Some(_)) => {}
+                _ => return Err(ConfigError::Source(script.name.clone())),
+            }
+        }
+        Ok(config)
+    }
+
+    /// The built-in rules minus the disabled ones, with the script rules
+    /// consulted first.
+    pub fn rules(&self) -> Result<Rules, ConfigError> {
+        let rules = self
+            .rules
+            .disable
+            .iter()
+            .try_fold(Rules::default(), |rules, name| {
+                if !rules.names().any(|n| n == name) {
+                    return Err(ConfigError::UnknownRule(name.clone()));
+                }
+                Ok(rules.without(name))
+            })?;
+        self.rules
+            .scripts
+            .iter()
+            .rev()
+            .try_fold(rules, |rules, script| {
+                let Some(source) = &script.source else {
+                    return Err(ConfigError::Source(script.name.clone()));
+                };
+                let rule = ScriptRule::new(&script.name, source).map_err(ConfigError::Script)?;
+                Ok(rules.with(rule))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests;
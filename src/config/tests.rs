@@ -0,0 +1,201 @@
+use super::*;
+use crate::diff::{DiffOptions, diff_specs_with};
+use crate::{Diff, Severity, loader};
+use tempfile::TempDir;
+
+fn spec(param: &str) -> openapiv3::OpenAPI {
+    loader::parse_str(&format!(
+        r#"
+openapi: "3.0.3"
+info:
+  title: Test
+  version: "1.0.0"
+paths:
+  /pets:
+    get:
+      parameters:
+        - name: {param}
+          in: query
+          schema:
+            type: string
+      responses:
+        "200":
+          description: OK
+"#
+    ))
+    .unwrap()
+}
+
+/// Write `config` and the given script files into a temporary directory and
+/// load it.
+fn load(config: &str, scripts: &[(&str, &str)]) -> Result<Config, ConfigError> {
+    let dir = TempDir::new().unwrap();
+    for (name, source) in scripts {
+        std::fs::write(dir.path().join(name), source).unwrap();
+    }
+    let path = dir.path().join("apidiff.yaml");
+    std::fs::write(&path, config).unwrap();
+    Config::load(&path)
+}
+
+fn diff_with(config: &Config, old: &str, new: &str) -> Diff {
+    let options = DiffOptions::new().rules(config.rules().unwrap());
+    diff_specs_with(&spec(old), &spec(new), &options)
+}
+
+fn rendered(diff: &Diff) -> Vec<String> {
+    diff.changes()
+        .iter()
+        .map(|c| format!("[{}] {c}", c.severity))
+        .collect()
+}
+
+const PARAMETER_CASE: &str = r#"
+if event.kind == "ParameterAdded" && old != () {
+    let renamed = old.parameters.some(|p| p.name != event.name && p.name.to_lower() == event.name.to_lower());
+    if renamed {
+        return #{
+            severity: "breaking",
+            findings: [#{ severity: "info", message: `was '${event.severity}' by rule '${event.rule}'` }],
+        };
+    }
+}
+"#;
+
+#[test]
+fn script_rules_override_severities_and_add_findings() {
+    let config = load(
+        "rules:\n  scripts:\n    - name: parameter-case\n      path: case.rhai\n",
+        &[("case.rhai", PARAMETER_CASE)],
+    )
+    .unwrap();
+    assert_eq!(
+        rendered(&diff_with(&config, "Limit", "limit")),
+        [
+            "[non-breaking] GET /pets - query parameter 'Limit': removed",
+            "[breaking] GET /pets - query parameter 'limit': added",
+            "[info] GET /pets - query parameter 'limit': was 'non-breaking' by rule 'parameters'",
+        ]
+    );
+    assert!(!diff_with(&config, "limit", "offset").has_breaking());
+}
+
+#[test]
+fn rules_can_be_disabled_and_scripts_given_inline() {
+    let config = load(
+        r#"
+rules:
+  disable: [parameters]
+  scripts:
+    - name: strict-parameters
+      source: |
+        if event.kind.starts_with("Parameter") { "warning" }
+"#,
+        &[],
+    )
+    .unwrap();
+    assert_eq!(
+        rendered(&diff_with(&config, "a", "b")),
+        [
+            "[warning] GET /pets - query parameter 'a': removed",
+            "[warning] GET /pets - query parameter 'b': added",
+        ]
+    );
+}
+
+#[test]
+fn scripts_are_sandboxed() {
+    let config = load(
+        r#"
+rules:
+  scripts:
+    - name: import
+      source: 'import "secrets" as s; "breaking"'
+    - name: eval
+      source: 'eval("40 + 2")'
+    - name: spin
+      source: 'loop {}'
+"#,
+        &[],
+    );
+    let err = config.unwrap().rules().err().unwrap();
+    assert!(err.to_string().starts_with("failed to compile rule 'eval'"));
+
+    let config = load(
+        r#"
+rules:
+  scripts:
+    - name: import
+      source: 'import "secrets" as s; "breaking"'
+    - name: spin
+      source: 'loop {}'
+"#,
+        &[],
+    )
+    .unwrap();
+    let diff = diff_with(&config, "a", "b");
+    assert!(!diff.has_breaking());
+    let failures: Vec<&str> = diff
+        .warnings()
+        .iter()
+        .map(|c| c.message.split(':').next().unwrap())
+        .collect();
+    // Reported once per location, however many events the rule failed on.
+    assert_eq!(failures, ["rule 'import' failed", "rule 'spin' failed"]);
+}
+
+#[test]
+fn invalid_configs_are_rejected() {
+    let missing = load("rules:\n  scripts:\n    - name: x\n", &[]).unwrap_err();
+    assert_eq!(
+        missing.to_string(),
+        "rule 'x' needs exactly one of 'path' or 'source'"
+    );
+
+    let unknown = load("rules:\n  disable: [enum]\n", &[]).unwrap();
+    assert_eq!(
+        unknown.rules().unwrap_err().to_string(),
+        "cannot disable unknown rule 'enum'"
+    );
+
+    let typo = load("rule: {}\n", &[]).unwrap_err();
+    assert!(matches!(typo, ConfigError::Parse(..)));
+
+    let result = load(
+        "rules:\n  scripts:\n    - name: x\n      path: nope.rhai\n",
+        &[],
+    );
+    assert!(matches!(result, Err(ConfigError::Io(path, _)) if path.ends_with("nope.rhai")));
+}
+
+#[test]
+fn scripts_see_the_event_severity_and_direction() {
+    let config = load(
+        r#"
+rules:
+  scripts:
+    - name: echo
+      source: |
+        #{ findings: [#{ severity: "info", message: `${event.kind} ${event.direction} ${event.severity}` }] }
+"#,
+        &[],
+    )
+    .unwrap();
+    let diff = diff_with(&config, "a", "a");
+    assert!(diff.is_empty());
+    let diff = diff_with(&config, "a", "b");
+    let info: Vec<&str> = diff.info().iter().map(|c| c.message.as_str()).collect();
+    assert_eq!(
+        info,
+        [
+            "ParameterRemoved request non-breaking",
+            "ParameterAdded request non-breaking",
+        ]
+    );
+    assert_eq!(diff.non_breaking().len(), 2);
+    assert!(
+        diff.changes()
+            .iter()
+            .all(|c| c.severity != Severity::Warning)
+    );
+}
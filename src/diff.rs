@@ -1,46 +1,153 @@
+//! Comparing two specs layer by layer.
+
 use openapiv3::{
-    Components, ObjectType, OpenAPI, Operation, Parameter, ParameterSchemaOrContent, PathItem,
-    ReferenceOr, RequestBody, Response, Responses, Schema, SchemaKind, StatusCode, StringType,
-    Type,
+    Callback, Components, Header, ObjectType, OpenAPI, Operation, Parameter,
+    ParameterSchemaOrContent, PathItem, ReferenceOr, RequestBody, Response, Responses, Schema,
+    SchemaKind, SecurityRequirement, Server, StatusCode, StringType, Type,
 };
 
+use indexmap::{IndexMap, IndexSet};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::fmt;
 use std::ops::Index;
 
-use crate::change::{Change, Location, Severity};
-
-#[derive(Debug)]
-pub struct Diff(Vec<Change>);
+use crate::change::{Change, Location, Severity, schema_steps};
+use media_type::MediaRange;
+
+mod event;
+mod media_type;
+mod rules;
+mod script;
+mod stability;
+mod tree;
+
+pub use event::{Event, EventKind};
+pub use rules::{Rule, RuleContext, Rules};
+pub use script::{ScriptError, ScriptRule};
+pub use tree::Node;
+
+/// The changes between two specs, ordered by spec structure.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Diff {
+    changes: Vec<Change>,
+    usages: Vec<Usage>,
+}
 
 impl Diff {
-    pub fn new(changes: Vec<Change>) -> Self {
-        Self(changes)
+    /// All changes, in spec order.
+    pub fn changes(&self) -> &[Change] {
+        &self.changes
     }
 
+    /// Whether the specs are equivalent.
     pub fn is_empty(&self) -> bool {
-        self.0.is_empty()
+        self.changes.is_empty()
     }
 
+    /// Whether any change breaks existing clients.
     pub fn has_breaking(&self) -> bool {
-        self.0.iter().any(|c| c.severity == Severity::Breaking)
+        self.changes
+            .iter()
+            .any(|c| c.severity == Severity::Breaking)
     }
 
+    /// Changes that break existing clients.
     pub fn breaking(&self) -> Vec<&Change> {
-        self.0
+        self.changes
             .iter()
             .filter(|c| c.severity == Severity::Breaking)
             .collect()
     }
 
+    /// Additions and relaxations clients can ignore.
     pub fn non_breaking(&self) -> Vec<&Change> {
-        self.0
+        self.changes
             .iter()
             .filter(|c| c.severity == Severity::NonBreaking)
             .collect()
     }
 
-    #[cfg(test)]
+    /// Legal changes that are still risky for some clients.
+    pub fn warnings(&self) -> Vec<&Change> {
+        self.changes
+            .iter()
+            .filter(|c| c.severity == Severity::Warning)
+            .collect()
+    }
+
+    /// Documentation-only changes.
+    pub fn info(&self) -> Vec<&Change> {
+        self.changes
+            .iter()
+            .filter(|c| c.severity == Severity::Info)
+            .collect()
+    }
+
+    /// Whether any change is at least as severe as `level`. Unstable changes
+    /// never count.
+    pub fn fails_on(&self, level: Severity) -> bool {
+        self.changes
+            .iter()
+            .any(|c| c.severity <= level && c.severity != Severity::Unstable)
+    }
+
+    /// Changes to operations marked with a non-stable `x-stability` level.
+    pub fn unstable(&self) -> Vec<&Change> {
+        self.changes
+            .iter()
+            .filter(|c| c.severity == Severity::Unstable)
+            .collect()
+    }
+
+    /// Operations (and callbacks/webhooks) affected by changes to a schema
+    /// component, following references from other components transitively.
+    /// Only populated when diffing with `DiffOptions::group_components`.
+    pub fn affected_by(&self, component: &str) -> Vec<&Usage> {
+        affected_usages(&self.usages, component, None)
+    }
+
+    /// Every direct reference to a schema component. Only populated when
+    /// diffing with `DiffOptions::group_components`.
+    pub fn usages(&self) -> &[Usage] {
+        &self.usages
+    }
+
+    /// Number of changes.
     pub fn len(&self) -> usize {
-        self.0.len()
+        self.changes.len()
+    }
+
+    /// The changes that are not in `previous` with the same severity, such
+    /// as those found since an earlier run was saved. Changes are matched by
+    /// fingerprint, so one judged differently, say under another removal
+    /// policy, is listed with its new severity.
+    pub fn since(&self, previous: &Diff) -> Diff {
+        let known: Vec<(String, Severity)> = previous
+            .changes
+            .iter()
+            .map(|c| (c.fingerprint(), c.severity))
+            .collect();
+        Diff {
+            changes: self
+                .changes
+                .iter()
+                .filter(|c| !known.contains(&(c.fingerprint(), c.severity)))
+                .cloned()
+                .collect(),
+            usages: self.usages.clone(),
+        }
+    }
+
+    /// The changes in `previous` that are no longer found, whatever their
+    /// severity: the counterpart of [`Diff::since`].
+    pub fn resolved_since<'a>(&self, previous: &'a Diff) -> Vec<&'a Change> {
+        let current: Vec<String> = self.changes.iter().map(Change::fingerprint).collect();
+        previous
+            .changes
+            .iter()
+            .filter(|c| !current.contains(&c.fingerprint()))
+            .collect()
     }
 }
 
@@ -48,57 +155,608 @@ impl Index<usize> for Diff {
     type Output = Change;
 
     fn index(&self, index: usize) -> &Change {
-        &self.0[index]
+        &self.changes[index]
     }
 }
 
-/// Compare two OpenAPI specs and return a list of changes.
+/// Use sites outside `components` that reach `component`, optionally only
+/// those in `direction`, following references from other components.
+fn affected_usages<'a>(
+    usages: &'a [Usage],
+    component: &str,
+    direction: Option<Direction>,
+) -> Vec<&'a Usage> {
+    let mut pending = vec![(component, direction)];
+    let mut seen: Vec<(&str, Option<Direction>)> = Vec::new();
+    let mut affected: Vec<&Usage> = Vec::new();
+
+    while let Some(key @ (name, direction)) = pending.pop() {
+        if seen.contains(&key) {
+            continue;
+        }
+        seen.push(key);
+        let direct = usages
+            .iter()
+            .filter(|u| u.component == name && direction.is_none_or(|d| d == u.direction));
+        for usage in direct {
+            match &usage.location {
+                Location::Component(parent) => pending.push((parent, Some(usage.direction))),
+                location => {
+                    let known = affected
+                        .iter()
+                        .any(|u| u.location == *location && u.direction == usage.direction);
+                    if !known {
+                        affected.push(usage);
+                    }
+                }
+            }
+        }
+    }
+
+    affected
+}
+
+/// A place where a schema component is referenced.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Usage {
+    /// Name of the component under `components/schemas`.
+    pub component: String,
+    /// The operation (or other component) referencing it.
+    pub location: Location,
+    /// Whether the reference is in a request or a response.
+    pub direction: Direction,
+}
+
+/// How to judge changes, built up from the defaults:
+///
+/// ```
+/// use apidiff::{DiffOptions, RemovalPolicy};
+///
+/// let options = DiffOptions::new()
+///     .group_components(true)
+///     .removal_policy(RemovalPolicy::AllowDeprecated);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct DiffOptions {
+    group_components: bool,
+    removal_policy: RemovalPolicy,
+    tolerant_reader: bool,
+    rules: Rules,
+}
+
+impl DiffOptions {
+    /// Default options: components are inlined, removals are strict and
+    /// clients are assumed to reject unknown enum values.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Diff each `$ref`'d schema component once, under `Location::Component`,
+    /// instead of inlining it at every use site. Use sites are recorded and
+    /// available through `Diff::affected_by`.
+    pub fn group_components(mut self, group: bool) -> Self {
+        self.group_components = group;
+        self
+    }
+
+    /// Whether removing something already deprecated in the old spec is
+    /// allowed. Applies to operations, parameters, response headers and
+    /// schema properties, and to paths whose operations are all deprecated.
+    pub fn removal_policy(mut self, policy: RemovalPolicy) -> Self {
+        self.removal_policy = policy;
+        self
+    }
+
+    /// Assume clients ignore unknown enum values, so adding one to a
+    /// response is a warning rather than breaking.
+    pub fn tolerant_reader(mut self, tolerant: bool) -> Self {
+        self.tolerant_reader = tolerant;
+        self
+    }
+
+    /// The rules classifying each change, `Rules::default()` unless
+    /// replaced.
+    pub fn rules(mut self, rules: Rules) -> Self {
+        self.rules = rules;
+        self
+    }
+}
+
+/// When removing a deprecated element stops being breaking.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RemovalPolicy {
+    /// Removals are judged the same whether or not they were deprecated.
+    #[default]
+    Strict,
+    /// Removing something marked `deprecated: true` is non-breaking.
+    AllowDeprecated,
+    /// Like `AllowDeprecated`, but only once its `x-sunset` date has passed.
+    /// Dates are compared as `YYYY-MM-DD` against `today`.
+    AfterSunset {
+        /// The current date, `YYYY-MM-DD`.
+        today: String,
+    },
+}
+
+/// State shared by all layers of one diff run.
+struct Ctx<'a> {
+    old: &'a OpenAPI,
+    new: &'a OpenAPI,
+    options: &'a DiffOptions,
+    usages: RefCell<Vec<Usage>>,
+}
+
+impl Ctx<'_> {
+    /// Whether the removal policy lets an element that was `deprecated` in
+    /// the old spec, with the given `x-sunset` date, be removed without
+    /// breaking.
+    fn removal_allowed(&self, deprecated: bool, sunset: Option<&str>) -> bool {
+        match &self.options.removal_policy {
+            RemovalPolicy::Strict => false,
+            RemovalPolicy::AllowDeprecated => deprecated,
+            RemovalPolicy::AfterSunset { today } => {
+                deprecated
+                    && sunset.is_some_and(|date| date.get(..10).unwrap_or(date) <= today.as_str())
+            }
+        }
+    }
+}
+
+/// Compare two OpenAPI specs with default options.
 pub fn diff_specs(old: &OpenAPI, new: &OpenAPI) -> Diff {
-    Diff::new(diff_paths(old, new))
+    diff_specs_with(old, new, &DiffOptions::default())
+}
+
+/// Compare two OpenAPI specs and return a list of changes.
+///
+/// The spec is walked layer by layer, collecting an `Event` for everything
+/// that differs; the rules in `options` then decide what each one means for
+/// clients.
+pub fn diff_specs_with(old: &OpenAPI, new: &OpenAPI, options: &DiffOptions) -> Diff {
+    let cx = Ctx {
+        old,
+        new,
+        options,
+        usages: RefCell::new(Vec::new()),
+    };
+    let servers = diff_servers(&Location::Document, &old.servers, &new.servers);
+    let info = [
+        diff_doc(
+            &Location::Document,
+            "info title",
+            Some(&old.info.title),
+            Some(&new.info.title),
+        ),
+        diff_doc(
+            &Location::Document,
+            "info description",
+            old.info.description.as_ref(),
+            new.info.description.as_ref(),
+        ),
+    ];
+    let events: Vec<Event> = servers
+        .into_iter()
+        .chain(info.into_iter().flatten())
+        .chain(diff_paths(&cx))
+        .chain(diff_webhooks(&cx))
+        .collect();
+    let changes = rules::classify(&cx, &events)
+        .into_iter()
+        .chain(diff_components(&cx))
+        .chain(rules::classify(&cx, &diff_component_deprecations(&cx)))
+        .collect();
+    let mut changes = dedup(stability::apply(&cx, changes));
+    changes.sort_by_cached_key(|c| structure_key(&c.location));
+    Diff {
+        changes,
+        usages: cx.usages.into_inner(),
+    }
+}
+
+/// Sort key placing a location by spec structure: path, method, then the
+/// operation's parameters, request body and responses. Servers come first at
+/// each level, webhooks after the paths and components last. Additions and
+/// removals are located at the element, so they sort within its section,
+/// and each step of a schema path is a level of its own, so a property's
+/// changes stay together with those below it. Changes at the same location
+/// keep the order the layers reported them in.
+fn structure_key(loc: &Location) -> Vec<(u8, String)> {
+    // Same order as `operations`.
+    const METHODS: [&str; 8] = [
+        "GET", "PUT", "POST", "DELETE", "OPTIONS", "HEAD", "PATCH", "TRACE",
+    ];
+    let mut key = loc.parent().map(structure_key).unwrap_or_default();
+    let step = match loc {
+        Location::Document => return key,
+        Location::Path(path) => (1, path.clone()),
+        Location::Operation { path, method } => {
+            key.push((1, path.clone()));
+            let rank = METHODS
+                .iter()
+                .position(|m| m == method)
+                .unwrap_or(METHODS.len());
+            (2, rank.to_string())
+        }
+        Location::Callback {
+            parent,
+            name,
+            inner,
+        } => {
+            key.extend(structure_key(parent));
+            key.push((11, name.clone()));
+            key.extend(structure_key(inner));
+            return key;
+        }
+        Location::Webhook(inner) => {
+            key.push((20, String::new()));
+            key.extend(structure_key(inner));
+            return key;
+        }
+        Location::Component(name) => (30, name.clone()),
+        Location::Server { url, .. } => (0, url.clone()),
+        Location::Parameter { name, r#in, .. } => (3, format!("{}:{name}", r#in)),
+        Location::RequestBody { .. } => (4, String::new()),
+        Location::Response { code, .. } => (5, code.clone()),
+        Location::Header { name, .. } => (6, name.to_ascii_lowercase()),
+        Location::MediaType { media, .. } => (7, media.clone()),
+        Location::Schema { path, .. } => {
+            key.extend(schema_steps(path).map(|step| (8, step.to_string())));
+            return key;
+        }
+        Location::Security { scheme, .. } => (9, scheme.clone()),
+    };
+    key.push(step);
+    key
+}
+
+/// Drop changes reported more than once, keeping the copy with the worst
+/// severity, so its message explains that severity, in the place of the
+/// first.
+fn dedup(changes: Vec<Change>) -> Vec<Change> {
+    let mut kept: Vec<Change> = Vec::with_capacity(changes.len());
+    let mut seen: IndexMap<String, usize> = IndexMap::new();
+    for change in changes {
+        match seen.get(&change.fingerprint()) {
+            Some(&i) if change.severity < kept[i].severity => kept[i] = change,
+            Some(_) => {}
+            None => {
+                seen.insert(change.fingerprint(), kept.len());
+                kept.push(change);
+            }
+        }
+    }
+    kept
 }
 
 // ---------------------------------------------------------------------------
-// Layer 1: Paths
+// Servers (document, path item and operation level)
 // ---------------------------------------------------------------------------
 
-fn diff_paths(old: &OpenAPI, new: &OpenAPI) -> Vec<Change> {
-    let removed = old.paths.iter().filter_map(|(path, _)| {
-        if new.paths.paths.contains_key(path) {
+fn diff_servers(loc: &Location, old: &[Server], new: &[Server]) -> Vec<Event> {
+    let existing =
+        old.iter().flat_map(
+            |old_s| match new.iter().find(|new_s| new_s.url == old_s.url) {
+                None => vec![Event::new(
+                    &loc.server(&old_s.url),
+                    EventKind::ServerRemoved {
+                        url: old_s.url.clone(),
+                    },
+                )],
+                Some(new_s) => diff_server_variables(loc, old_s, new_s),
+            },
+        );
+
+    let added = new.iter().filter_map(|new_s| {
+        if old.iter().any(|old_s| old_s.url == new_s.url) {
             None
         } else {
-            Some(Change {
-                severity: Severity::Breaking,
-                location: Location::Path(path.clone()),
-                message: "endpoint removed".into(),
-            })
+            Some(Event::new(
+                &loc.server(&new_s.url),
+                EventKind::ServerAdded {
+                    url: new_s.url.clone(),
+                },
+            ))
         }
     });
 
-    let added = new.paths.paths.keys().filter_map(|path| {
-        if old.paths.paths.contains_key(path) {
+    existing.chain(added).collect()
+}
+
+fn diff_server_variables(loc: &Location, old: &Server, new: &Server) -> Vec<Event> {
+    let empty = IndexMap::new();
+    let old_vars = old.variables.as_ref().unwrap_or(&empty);
+    let new_vars = new.variables.as_ref().unwrap_or(&empty);
+    let loc = &loc.server(&old.url);
+
+    let existing = old_vars
+        .iter()
+        .flat_map(|(name, old_var)| match new_vars.get(name) {
+            None => vec![Event::new(
+                loc,
+                EventKind::ServerVariableRemoved { name: name.clone() },
+            )],
+            Some(new_var) => {
+                let default = if old_var.default != new_var.default {
+                    Some(Event::new(
+                        loc,
+                        EventKind::ServerVariableDefaultChanged {
+                            name: name.clone(),
+                            from: old_var.default.clone(),
+                            to: new_var.default.clone(),
+                        },
+                    ))
+                } else {
+                    None
+                };
+                let removed = old_var
+                    .enumeration
+                    .iter()
+                    .filter(|v| !new_var.enumeration.contains(v))
+                    .map(|v| {
+                        Event::new(
+                            loc,
+                            EventKind::ServerVariableValueRemoved {
+                                name: name.clone(),
+                                value: v.clone(),
+                            },
+                        )
+                    });
+                let added = new_var
+                    .enumeration
+                    .iter()
+                    .filter(|v| !old_var.enumeration.contains(v))
+                    .map(|v| {
+                        Event::new(
+                            loc,
+                            EventKind::ServerVariableValueAdded {
+                                name: name.clone(),
+                                value: v.clone(),
+                            },
+                        )
+                    });
+                default.into_iter().chain(removed).chain(added).collect()
+            }
+        });
+
+    let added = new_vars.keys().filter_map(|name| {
+        if old_vars.contains_key(name) {
             None
         } else {
-            Some(Change {
-                severity: Severity::NonBreaking,
-                location: Location::Path(path.clone()),
-                message: "endpoint added".into(),
-            })
+            Some(Event::new(
+                loc,
+                EventKind::ServerVariableAdded { name: name.clone() },
+            ))
         }
     });
 
-    let shared = old.paths.iter().flat_map(|(path, old_ref)| {
-        new.paths
-            .paths
-            .get(path)
+    existing.chain(added).collect()
+}
+
+/// Servers that apply to an operation: the most specific non-empty list wins.
+fn effective_servers<'a>(spec: &'a OpenAPI, item: &'a PathItem, op: &'a Operation) -> &'a [Server] {
+    if !op.servers.is_empty() {
+        &op.servers
+    } else if !item.servers.is_empty() {
+        &item.servers
+    } else {
+        &spec.servers
+    }
+}
+
+/// Path component of a server URL with variables replaced by their defaults.
+fn base_path(server: &Server) -> String {
+    let url = server
+        .variables
+        .iter()
+        .flatten()
+        .fold(server.url.clone(), |url, (name, var)| {
+            url.replace(&format!("{{{name}}}"), &var.default)
+        });
+    let path = match url.split_once("://") {
+        Some((_, rest)) => rest.find('/').map_or("", |i| &rest[i..]),
+        None => url.as_str(),
+    };
+    match path.trim_end_matches('/') {
+        "" => "/".into(),
+        p => p.into(),
+    }
+}
+
+fn base_paths(servers: &[Server]) -> Vec<String> {
+    if servers.is_empty() {
+        return vec!["/".into()];
+    }
+    servers
+        .iter()
+        .map(base_path)
+        .fold(Vec::new(), |mut acc, p| {
+            if !acc.contains(&p) {
+                acc.push(p);
+            }
+            acc
+        })
+}
+
+fn diff_base_paths(loc: &Location, old: &[String], new: &[String]) -> Vec<Event> {
+    let removed: Vec<_> = old.iter().filter(|p| !new.contains(p)).collect();
+    let added: Vec<_> = new.iter().filter(|p| !old.contains(p)).collect();
+
+    match (removed.as_slice(), added.as_slice()) {
+        ([from], [to]) => vec![Event::new(
+            loc,
+            EventKind::BasePathChanged {
+                from: from.to_string(),
+                to: to.to_string(),
+            },
+        )],
+        _ => removed
             .into_iter()
-            .flat_map(
-                move |new_ref| match (old_ref.as_item(), new_ref.as_item()) {
-                    (Some(old_item), Some(new_item)) => {
-                        diff_path_item(path, old_item, new_item, old, new)
-                    }
-                    _ => vec![],
-                },
-            )
+            .map(|p| Event::new(loc, EventKind::BasePathRemoved { path: p.clone() }))
+            .collect(),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Layer 1: Paths
+// ---------------------------------------------------------------------------
+
+fn diff_paths(cx: &Ctx) -> Vec<Event> {
+    diff_path_items(
+        &cx.old.paths.paths,
+        &cx.new.paths.paths,
+        ReferenceOr::as_item,
+        cx,
+        &|loc| loc,
+        Direction::Request,
+    )
+}
+
+/// Diff two maps of path items keyed by path (or callback expression).
+///
+/// `locate` wraps each path/operation location into the scope the items were
+/// reached from, and `flow` is the direction of their operations' requests:
+/// `Request` for the API's own paths, `Response` for callbacks and webhooks,
+/// where the API provider is the one sending the request.
+fn diff_path_items<T>(
+    old: &IndexMap<String, T>,
+    new: &IndexMap<String, T>,
+    as_item: fn(&T) -> Option<&PathItem>,
+    cx: &Ctx,
+    locate: &dyn Fn(Location) -> Location,
+    flow: Direction,
+) -> Vec<Event> {
+    let removed = old.iter().filter_map(|(path, old_ref)| {
+        if new.contains_key(path) {
+            None
+        } else {
+            let mut event = Event::new(
+                &locate(Location::Path(path.clone())),
+                EventKind::PathRemoved,
+            );
+            if let Some(item) = as_item(old_ref) {
+                (event.deprecated, event.sunset) = retirement(item);
+            }
+            Some(event)
+        }
+    });
+
+    let added = new.keys().filter_map(|path| {
+        if old.contains_key(path) {
+            None
+        } else {
+            Some(Event::new(
+                &locate(Location::Path(path.clone())),
+                EventKind::PathAdded,
+            ))
+        }
+    });
+
+    let shared = old.iter().flat_map(|(path, old_ref)| {
+        new.get(path).into_iter().flat_map(move |new_ref| {
+            match (as_item(old_ref), as_item(new_ref)) {
+                (Some(old_item), Some(new_item)) => {
+                    diff_path_item(path, old_item, new_item, cx, locate, flow)
+                }
+                _ => vec![],
+            }
+        })
+    });
+
+    removed.chain(added).chain(shared).collect()
+}
+
+/// Whether a path item can go as a deprecated element: when all of its
+/// operations are deprecated, with the latest of their `x-sunset` dates if
+/// every one has a date.
+fn retirement(item: &PathItem) -> (bool, Option<String>) {
+    let ops: Vec<&Operation> = operations(item)
+        .into_iter()
+        .filter_map(|(_, op)| op.as_ref())
+        .collect();
+    let deprecated = !ops.is_empty() && ops.iter().all(|op| op.deprecated);
+    let sunsets: Option<Vec<&str>> = ops
+        .iter()
+        .map(|op| op.extensions.get("x-sunset").and_then(|v| v.as_str()))
+        .collect();
+    let sunset = sunsets.and_then(|dates| dates.into_iter().max().map(str::to_string));
+    (deprecated, sunset)
+}
+
+// ---------------------------------------------------------------------------
+// Webhooks and callbacks (requests sent by the API provider)
+// ---------------------------------------------------------------------------
+
+/// Where webhooks are declared: the 3.0 `x-webhooks` extension, and the
+/// OpenAPI 3.1 `webhooks` map, which the loader keeps among the extensions
+/// since the `openapiv3` model only covers 3.0.
+pub(crate) const WEBHOOKS: [&str; 2] = ["x-webhooks", "webhooks"];
+
+/// Webhooks declared under either key; a name declared under both is taken
+/// from `webhooks`. The loader rejects webhooks that aren't path items, so a
+/// spec built without it is the only way to get here with malformed ones.
+fn webhooks(spec: &OpenAPI) -> IndexMap<String, PathItem> {
+    WEBHOOKS
+        .iter()
+        .filter_map(|key| spec.extensions.get(*key))
+        .filter_map(|v| serde_json::from_value::<IndexMap<String, PathItem>>(v.clone()).ok())
+        .flatten()
+        .collect()
+}
+
+fn inline_item(item: &PathItem) -> Option<&PathItem> {
+    Some(item)
+}
+
+fn diff_webhooks(cx: &Ctx) -> Vec<Event> {
+    diff_path_items(
+        &webhooks(cx.old),
+        &webhooks(cx.new),
+        inline_item,
+        cx,
+        &|loc| Location::Webhook(Box::new(loc)),
+        Direction::Response,
+    )
+}
+
+fn diff_callbacks(
+    loc: &Location,
+    old: &IndexMap<String, Callback>,
+    new: &IndexMap<String, Callback>,
+    cx: &Ctx,
+    flow: Direction,
+) -> Vec<Event> {
+    let removed = old.keys().filter_map(|name| {
+        if new.contains_key(name) {
+            None
+        } else {
+            Some(Event::new(
+                loc,
+                EventKind::CallbackRemoved { name: name.clone() },
+            ))
+        }
+    });
+
+    let added = new.keys().filter_map(|name| {
+        if old.contains_key(name) {
+            None
+        } else {
+            Some(Event::new(
+                loc,
+                EventKind::CallbackAdded { name: name.clone() },
+            ))
+        }
+    });
+
+    let shared = old.iter().flat_map(|(name, old_cb)| {
+        new.get(name).into_iter().flat_map(move |new_cb| {
+            let locate = |inner| Location::Callback {
+                parent: Box::new(loc.clone()),
+                name: name.clone(),
+                inner: Box::new(inner),
+            };
+            diff_path_items(old_cb, new_cb, inline_item, cx, &locate, flow.reverse())
+        })
     });
 
     removed.chain(added).chain(shared).collect()
@@ -125,35 +783,50 @@ fn diff_path_item(
     path: &str,
     old: &PathItem,
     new: &PathItem,
-    old_spec: &OpenAPI,
-    new_spec: &OpenAPI,
-) -> Vec<Change> {
-    operations(old)
-        .into_iter()
-        .zip(operations(new))
-        .flat_map(|((method, old_op), (_, new_op))| {
-            let location = Location::Operation {
+    cx: &Ctx,
+    locate: &dyn Fn(Location) -> Location,
+    flow: Direction,
+) -> Vec<Event> {
+    let servers = diff_servers(
+        &locate(Location::Path(path.to_string())),
+        &old.servers,
+        &new.servers,
+    );
+
+    let ops = operations(old).into_iter().zip(operations(new)).flat_map(
+        |((method, old_op), (_, new_op))| {
+            let location = locate(Location::Operation {
                 path: path.to_string(),
                 method: method.to_string(),
-            };
+            });
             match (old_op, new_op) {
-                (Some(_), None) => vec![Change {
-                    severity: Severity::Breaking,
-                    location,
-                    message: "operation removed".into(),
-                }],
-                (None, Some(_)) => vec![Change {
-                    severity: Severity::NonBreaking,
-                    location,
-                    message: "operation added".into(),
-                }],
+                (Some(old_op), None) => vec![
+                    Event::new(&location, EventKind::OperationRemoved)
+                        .retiring(old_op.deprecated, &old_op.extensions),
+                ],
+                (None, Some(_)) => vec![Event::new(&location, EventKind::OperationAdded)],
                 (Some(old_op), Some(new_op)) => {
-                    diff_operation(&location, old_op, new_op, old_spec, new_spec)
+                    // Callback and webhook URLs belong to the consumer, so the
+                    // spec's servers only apply to the API's own operations.
+                    let bases = match flow {
+                        Direction::Request => diff_base_paths(
+                            &location,
+                            &base_paths(effective_servers(cx.old, old, old_op)),
+                            &base_paths(effective_servers(cx.new, new, new_op)),
+                        ),
+                        Direction::Response => vec![],
+                    };
+                    bases
+                        .into_iter()
+                        .chain(diff_operation(&location, old_op, new_op, cx, flow))
+                        .collect()
                 }
                 (None, None) => vec![],
             }
-        })
-        .collect()
+        },
+    );
+
+    servers.into_iter().chain(ops).collect()
 }
 
 // ---------------------------------------------------------------------------
@@ -164,43 +837,171 @@ fn diff_operation(
     loc: &Location,
     old: &Operation,
     new: &Operation,
-    old_spec: &OpenAPI,
-    new_spec: &OpenAPI,
-) -> Vec<Change> {
-    let params = diff_parameters(
-        loc,
-        &old.parameters,
-        &new.parameters,
-        &old_spec.components,
-        &new_spec.components,
-    );
-    let body = diff_request_body(
+    cx: &Ctx,
+    flow: Direction,
+) -> Vec<Event> {
+    let params = diff_parameters(loc, &old.parameters, &new.parameters, cx, flow);
+    let body = diff_request_body(loc, &old.request_body, &new.request_body, cx, flow);
+    let responses = diff_responses(loc, &old.responses, &new.responses, cx, flow.reverse());
+    let servers = diff_servers(loc, &old.servers, &new.servers);
+    let callbacks = diff_callbacks(loc, &old.callbacks, &new.callbacks, cx, flow);
+    // Only the API's own operations are called with the spec's credentials.
+    let security = match flow {
+        Direction::Request => diff_security(
+            loc,
+            effective_security(cx.old, old),
+            effective_security(cx.new, new),
+        ),
+        Direction::Response => vec![],
+    };
+
+    let deprecated = diff_deprecated(loc, "operation", old.deprecated, new.deprecated);
+    let summary = diff_doc(loc, "summary", old.summary.as_ref(), new.summary.as_ref());
+    let description = diff_doc(
         loc,
-        &old.request_body,
-        &new.request_body,
-        old_spec,
-        new_spec,
+        "description",
+        old.description.as_ref(),
+        new.description.as_ref(),
     );
-    let responses = diff_responses(loc, &old.responses, &new.responses, old_spec, new_spec);
-
-    let deprecated = if !old.deprecated && new.deprecated {
-        Some(Change {
-            severity: Severity::NonBreaking,
-            location: loc.clone(),
-            message: "operation deprecated".into(),
-        })
-    } else {
-        None
-    };
+    let tags = diff_tags(loc, &old.tags, &new.tags);
 
     params
         .into_iter()
         .chain(body)
         .chain(responses)
+        .chain(servers)
+        .chain(callbacks)
+        .chain(security)
         .chain(deprecated)
+        .chain(summary)
+        .chain(description)
+        .chain(tags)
         .collect()
 }
 
+/// Report a documentation-only field that was added, removed or edited.
+fn diff_doc<T: PartialEq>(
+    loc: &Location,
+    subject: &str,
+    old: Option<&T>,
+    new: Option<&T>,
+) -> Option<Event> {
+    let field = subject.to_string();
+    let kind = match (old, new) {
+        (None, Some(_)) => EventKind::DocumentationAdded { field },
+        (Some(_), None) => EventKind::DocumentationRemoved { field },
+        (Some(old), Some(new)) if old != new => EventKind::DocumentationChanged { field },
+        _ => return None,
+    };
+    Some(Event::new(loc, kind))
+}
+
+/// `examples` maps as an optional field, so an empty map counts as absent.
+fn examples<T>(map: &IndexMap<String, T>) -> Option<&IndexMap<String, T>> {
+    (!map.is_empty()).then_some(map)
+}
+
+fn diff_tags(loc: &Location, old: &[String], new: &[String]) -> Vec<Event> {
+    let removed = old
+        .iter()
+        .filter(|t| !new.contains(t))
+        .map(|t| Event::new(loc, EventKind::TagRemoved { tag: t.clone() }));
+    let added = new
+        .iter()
+        .filter(|t| !old.contains(t))
+        .map(|t| Event::new(loc, EventKind::TagAdded { tag: t.clone() }));
+    removed.chain(added).collect()
+}
+
+/// Security requirements of an operation: its own, or else the document's.
+fn effective_security<'a>(spec: &'a OpenAPI, op: &'a Operation) -> &'a [SecurityRequirement] {
+    op.security
+        .as_deref()
+        .or(spec.security.as_deref())
+        .unwrap_or(&[])
+}
+
+fn requirement_label(req: &SecurityRequirement) -> String {
+    let mut schemes: Vec<&str> = req.keys().map(String::as_str).collect();
+    schemes.sort_unstable();
+    match schemes.as_slice() {
+        [] => "anonymous".into(),
+        schemes => schemes.join(" + "),
+    }
+}
+
+/// Each requirement is an alternative: clients satisfy one of them, using
+/// all of its schemes together. An empty list, or an empty requirement,
+/// lets clients call without credentials.
+fn diff_security(
+    loc: &Location,
+    old: &[SecurityRequirement],
+    new: &[SecurityRequirement],
+) -> Vec<Event> {
+    let same_schemes = |a: &SecurityRequirement, b: &SecurityRequirement| {
+        a.len() == b.len() && a.keys().all(|k| b.contains_key(k))
+    };
+
+    // Clients that can call without credentials lose nothing when a
+    // requirement goes, and need no new alternative when one is added.
+    let still_open = new.is_empty() || new.iter().any(|r| r.is_empty());
+    let existing = old.iter().flat_map(|old_req| {
+        match new.iter().find(|new_req| same_schemes(old_req, new_req)) {
+            None => vec![Event::new(
+                loc,
+                EventKind::SecurityRequirementRemoved {
+                    requirement: requirement_label(old_req),
+                    still_open,
+                },
+            )],
+            Some(new_req) => old_req
+                .iter()
+                .flat_map(|(scheme, old_scopes)| {
+                    let new_scopes = &new_req[scheme];
+                    let loc = loc.security(scheme);
+                    let added = new_scopes
+                        .iter()
+                        .filter(|s| !old_scopes.contains(s))
+                        .map(|s| Event::new(&loc, EventKind::ScopeAdded { scope: s.clone() }));
+                    let removed = old_scopes
+                        .iter()
+                        .filter(|s| !new_scopes.contains(s))
+                        .map(|s| Event::new(&loc, EventKind::ScopeRemoved { scope: s.clone() }));
+                    added.chain(removed).collect::<Vec<_>>()
+                })
+                .collect(),
+        }
+    });
+
+    let newly_required = old.is_empty() && !still_open;
+    let added = new
+        .iter()
+        .filter(|new_req| !old.iter().any(|old_req| same_schemes(old_req, new_req)))
+        .map(|new_req| {
+            Event::new(
+                loc,
+                EventKind::SecurityRequirementAdded {
+                    requirement: requirement_label(new_req),
+                    newly_required,
+                },
+            )
+        });
+
+    existing.chain(added).collect()
+}
+
+/// Report `subject` becoming deprecated, or no longer being deprecated. An
+/// empty subject means the element at `loc` itself.
+fn diff_deprecated(loc: &Location, subject: &str, old: bool, new: bool) -> Option<Event> {
+    let subject = subject.to_string();
+    let kind = match (old, new) {
+        (false, true) => EventKind::Deprecated { subject },
+        (true, false) => EventKind::Undeprecated { subject },
+        _ => return None,
+    };
+    Some(Event::new(loc, kind))
+}
+
 // ---------------------------------------------------------------------------
 // Layer 4: Parameters
 // ---------------------------------------------------------------------------
@@ -246,57 +1047,70 @@ fn diff_parameters(
     loc: &Location,
     old_params: &[ReferenceOr<Parameter>],
     new_params: &[ReferenceOr<Parameter>],
-    old_components: &Option<Components>,
-    new_components: &Option<Components>,
-) -> Vec<Change> {
-    let old_map: std::collections::HashMap<ParamKey, &Parameter> = old_params
+    cx: &Ctx,
+    direction: Direction,
+) -> Vec<Event> {
+    let old_map: IndexMap<ParamKey, &Parameter> = old_params
         .iter()
-        .filter_map(|r| resolve_param(r, old_components))
+        .filter_map(|r| resolve_param(r, &cx.old.components))
         .map(|p| (param_key(p), p))
         .collect();
 
-    let new_map: std::collections::HashMap<ParamKey, &Parameter> = new_params
+    let new_map: IndexMap<ParamKey, &Parameter> = new_params
         .iter()
-        .filter_map(|r| resolve_param(r, new_components))
+        .filter_map(|r| resolve_param(r, &cx.new.components))
         .map(|p| (param_key(p), p))
         .collect();
 
     let existing = old_map.iter().flat_map(|(key, old_p)| {
         let old_data = old_p.parameter_data_ref();
         match new_map.get(key) {
-            None => {
-                vec![Change {
-                    severity: if old_data.required {
-                        Severity::Breaking
-                    } else {
-                        Severity::NonBreaking
+            None => vec![
+                Event::new(
+                    &loc.parameter(&key.name, &key.location),
+                    EventKind::ParameterRemoved {
+                        name: key.name.clone(),
+                        r#in: key.location.clone(),
+                        required: old_data.required,
                     },
-                    location: loc.clone(),
-                    message: format!("{} parameter '{}' removed", key.location, key.name),
-                }]
-            }
+                )
+                .flowing(direction)
+                .retiring(old_data.deprecated.unwrap_or(false), &old_data.extensions),
+            ],
             Some(new_p) => {
                 let new_data = new_p.parameter_data_ref();
+                let loc = &loc.parameter(&key.name, &key.location);
                 let mut changes = Vec::new();
                 if !old_data.required && new_data.required {
-                    changes.push(Change {
-                        severity: Severity::Breaking,
-                        location: loc.clone(),
-                        message: format!("parameter '{}' became required", key.name),
-                    });
+                    changes.push(Event::new(loc, EventKind::BecameRequired).flowing(direction));
                 }
                 if old_data.required && !new_data.required {
-                    changes.push(Change {
-                        severity: Severity::NonBreaking,
-                        location: loc.clone(),
-                        message: format!("parameter '{}' became optional", key.name),
-                    });
+                    changes.push(Event::new(loc, EventKind::BecameOptional).flowing(direction));
                 }
-                changes.extend(diff_parameter_type(
+                changes.extend(diff_parameter_type(loc, &old_data.format, &new_data.format));
+                changes.extend(diff_deprecated(
+                    loc,
+                    "",
+                    old_data.deprecated.unwrap_or(false),
+                    new_data.deprecated.unwrap_or(false),
+                ));
+                changes.extend(diff_doc(
+                    loc,
+                    "description",
+                    old_data.description.as_ref(),
+                    new_data.description.as_ref(),
+                ));
+                changes.extend(diff_doc(
                     loc,
-                    &key.name,
-                    &old_data.format,
-                    &new_data.format,
+                    "example",
+                    old_data.example.as_ref(),
+                    new_data.example.as_ref(),
+                ));
+                changes.extend(diff_doc(
+                    loc,
+                    "examples",
+                    examples(&old_data.examples),
+                    examples(&new_data.examples),
                 ));
                 changes
             }
@@ -307,29 +1121,25 @@ fn diff_parameters(
         if old_map.contains_key(key) {
             None
         } else {
-            let new_data = new_p.parameter_data_ref();
-            let sev = if new_data.required {
-                Severity::Breaking
-            } else {
-                Severity::NonBreaking
+            let kind = EventKind::ParameterAdded {
+                name: key.name.clone(),
+                r#in: key.location.clone(),
+                required: new_p.parameter_data_ref().required,
             };
-            Some(Change {
-                severity: sev,
-                location: loc.clone(),
-                message: format!("{} parameter '{}' added", key.location, key.name),
-            })
+            let loc = loc.parameter(&key.name, &key.location);
+            Some(Event::new(&loc, kind).flowing(direction))
         }
     });
 
     existing.chain(added).collect()
 }
 
+/// Compare the schema types of a parameter or header.
 fn diff_parameter_type(
     loc: &Location,
-    name: &str,
     old_format: &ParameterSchemaOrContent,
     new_format: &ParameterSchemaOrContent,
-) -> Vec<Change> {
+) -> Vec<Event> {
     let old_schema = match old_format {
         ParameterSchemaOrContent::Schema(r) => r.as_item(),
         _ => None,
@@ -342,16 +1152,13 @@ fn diff_parameter_type(
         (Some(old_s), Some(new_s))
             if type_name(&old_s.schema_kind) != type_name(&new_s.schema_kind) =>
         {
-            vec![Change {
-                severity: Severity::Breaking,
-                location: loc.clone(),
-                message: format!(
-                    "parameter '{}' type changed from {} to {}",
-                    name,
-                    type_name(&old_s.schema_kind),
-                    type_name(&new_s.schema_kind),
-                ),
-            }]
+            vec![Event::new(
+                loc,
+                EventKind::TypeChanged {
+                    from: type_name(&old_s.schema_kind),
+                    to: type_name(&new_s.schema_kind),
+                },
+            )]
         }
         _ => vec![],
     }
@@ -383,51 +1190,30 @@ fn diff_request_body(
     loc: &Location,
     old: &Option<ReferenceOr<RequestBody>>,
     new: &Option<ReferenceOr<RequestBody>>,
-    old_spec: &OpenAPI,
-    new_spec: &OpenAPI,
-) -> Vec<Change> {
-    let old_rb = old.as_ref().and_then(|r| resolve_request_body(r, old_spec));
-    let new_rb = new.as_ref().and_then(|r| resolve_request_body(r, new_spec));
+    cx: &Ctx,
+    direction: Direction,
+) -> Vec<Event> {
+    let old_rb = old.as_ref().and_then(|r| resolve_request_body(r, cx.old));
+    let new_rb = new.as_ref().and_then(|r| resolve_request_body(r, cx.new));
 
+    let loc = &loc.request_body();
     match (old_rb, new_rb) {
         (None, Some(rb)) => {
-            let sev = if rb.required {
-                Severity::Breaking
-            } else {
-                Severity::NonBreaking
+            let kind = EventKind::RequestBodyAdded {
+                required: rb.required,
             };
-            vec![Change {
-                severity: sev,
-                location: loc.clone(),
-                message: "request body added".into(),
-            }]
+            vec![Event::new(loc, kind).flowing(direction)]
         }
         (Some(_), None) => {
-            vec![Change {
-                severity: Severity::Breaking,
-                location: loc.clone(),
-                message: "request body removed".into(),
-            }]
+            vec![Event::new(loc, EventKind::RequestBodyRemoved).flowing(direction)]
         }
         (Some(old_rb), Some(new_rb)) => {
             let required = if !old_rb.required && new_rb.required {
-                Some(Change {
-                    severity: Severity::Breaking,
-                    location: loc.clone(),
-                    message: "request body became required".into(),
-                })
+                Some(Event::new(loc, EventKind::BecameRequired).flowing(direction))
             } else {
                 None
             };
-            let content = diff_content(
-                loc,
-                "request body",
-                &old_rb.content,
-                &new_rb.content,
-                Direction::Request,
-                old_spec,
-                new_spec,
-            );
+            let content = diff_content(loc, &old_rb.content, &new_rb.content, direction, cx);
             required.into_iter().chain(content).collect()
         }
         (None, None) => vec![],
@@ -460,131 +1246,430 @@ fn status_code_str(sc: &StatusCode) -> String {
     }
 }
 
-fn diff_responses(
-    loc: &Location,
-    old: &Responses,
-    new: &Responses,
-    old_spec: &OpenAPI,
-    new_spec: &OpenAPI,
-) -> Vec<Change> {
-    let existing = old
+/// The response that answers `code` when it is not listed explicitly: the
+/// matching `NXX` range for a concrete code, then `default`.
+fn fallback_response<'a>(
+    responses: &'a Responses,
+    code: &StatusCode,
+) -> Option<(String, &'a ReferenceOr<Response>)> {
+    let range = match code {
+        StatusCode::Code(c) => {
+            let range = StatusCode::Range(c / 100);
+            responses
+                .responses
+                .get(&range)
+                .map(|r| (status_code_str(&range), r))
+        }
+        StatusCode::Range(_) => None,
+    };
+    range.or_else(|| responses.default.as_ref().map(|r| ("default".into(), r)))
+}
+
+/// The code clients treat as success: the lowest explicit 2xx code.
+fn primary_success(responses: &Responses) -> Option<u16> {
+    responses
         .responses
+        .keys()
+        .filter_map(|code| match code {
+            StatusCode::Code(c @ 200..=299) => Some(*c),
+            _ => None,
+        })
+        .min()
+}
+
+/// Compare a response pair; `loc` is the response's own location.
+fn diff_response(
+    loc: &Location,
+    old: &ReferenceOr<Response>,
+    new: &ReferenceOr<Response>,
+    cx: &Ctx,
+    direction: Direction,
+) -> Vec<Event> {
+    match (resolve_response(old, cx.old), resolve_response(new, cx.new)) {
+        (Some(old_resp), Some(new_resp)) => diff_doc(
+            loc,
+            "description",
+            Some(&old_resp.description),
+            Some(&new_resp.description),
+        )
+        .into_iter()
+        .chain(diff_headers(
+            loc,
+            &old_resp.headers,
+            &new_resp.headers,
+            cx,
+            direction,
+        ))
+        .chain(diff_content(
+            loc,
+            &old_resp.content,
+            &new_resp.content,
+            direction,
+            cx,
+        ))
+        .collect(),
+        _ => vec![],
+    }
+}
+
+fn resolve_header<'a>(r: &'a ReferenceOr<Header>, spec: &'a OpenAPI) -> Option<&'a Header> {
+    match r {
+        ReferenceOr::Item(h) => Some(h),
+        ReferenceOr::Reference { reference } => {
+            let prefix = "#/components/headers/";
+            reference.strip_prefix(prefix).and_then(|name| {
+                spec.components
+                    .as_ref()
+                    .and_then(|c| c.headers.get(name))
+                    .and_then(|r| r.as_item())
+            })
+        }
+    }
+}
+
+/// Resolved headers with their names; header names are case-insensitive.
+fn resolve_headers<'a>(
+    headers: &'a IndexMap<String, ReferenceOr<Header>>,
+    spec: &'a OpenAPI,
+) -> Vec<(&'a str, &'a Header)> {
+    headers
         .iter()
-        .flat_map(|(code, old_ref)| match new.responses.get(code) {
-            None => {
-                vec![Change {
-                    severity: Severity::Breaking,
-                    location: loc.clone(),
-                    message: format!("response '{}' removed", status_code_str(code)),
-                }]
-            }
-            Some(new_ref) => {
-                match (
-                    resolve_response(old_ref, old_spec),
-                    resolve_response(new_ref, new_spec),
-                ) {
-                    (Some(old_resp), Some(new_resp)) => {
-                        let label = format!("response '{}'", status_code_str(code));
-                        diff_content(
-                            loc,
-                            &label,
-                            &old_resp.content,
-                            &new_resp.content,
-                            Direction::Response,
-                            old_spec,
-                            new_spec,
-                        )
+        .filter_map(|(name, r)| Some((name.as_str(), resolve_header(r, spec)?)))
+        .collect()
+}
+
+fn find_header<'a>(headers: &[(&str, &'a Header)], name: &str) -> Option<&'a Header> {
+    headers
+        .iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        .map(|&(_, h)| h)
+}
+
+fn diff_headers(
+    loc: &Location,
+    old: &IndexMap<String, ReferenceOr<Header>>,
+    new: &IndexMap<String, ReferenceOr<Header>>,
+    cx: &Ctx,
+    direction: Direction,
+) -> Vec<Event> {
+    let old_headers = resolve_headers(old, cx.old);
+    let new_headers = resolve_headers(new, cx.new);
+
+    let existing =
+        old_headers
+            .iter()
+            .flat_map(|&(name, old_h)| match find_header(&new_headers, name) {
+                None => vec![
+                    Event::new(
+                        &loc.header(name),
+                        EventKind::HeaderRemoved {
+                            name: name.to_string(),
+                        },
+                    )
+                    .flowing(direction)
+                    .retiring(old_h.deprecated.unwrap_or(false), &old_h.extensions),
+                ],
+                Some(new_h) => {
+                    let loc = &loc.header(name);
+                    let mut changes = Vec::new();
+                    if !old_h.required && new_h.required {
+                        changes.push(Event::new(loc, EventKind::BecameRequired).flowing(direction));
                     }
-                    _ => vec![],
+                    if old_h.required && !new_h.required {
+                        changes.push(Event::new(loc, EventKind::BecameOptional).flowing(direction));
+                    }
+                    changes.extend(diff_parameter_type(loc, &old_h.format, &new_h.format));
+                    changes.extend(diff_deprecated(
+                        loc,
+                        "",
+                        old_h.deprecated.unwrap_or(false),
+                        new_h.deprecated.unwrap_or(false),
+                    ));
+                    changes.extend(diff_doc(
+                        loc,
+                        "description",
+                        old_h.description.as_ref(),
+                        new_h.description.as_ref(),
+                    ));
+                    changes
                 }
-            }
+            });
+
+    let added = new_headers
+        .iter()
+        .filter(|&&(name, _)| find_header(&old_headers, name).is_none())
+        .map(|&(name, new_h)| {
+            let kind = EventKind::HeaderAdded {
+                name: name.to_string(),
+                required: new_h.required,
+            };
+            Event::new(&loc.header(name), kind).flowing(direction)
         });
 
+    existing.chain(added).collect()
+}
+
+fn diff_responses(
+    loc: &Location,
+    old: &Responses,
+    new: &Responses,
+    cx: &Ctx,
+    direction: Direction,
+) -> Vec<Event> {
+    // A success code replaced by one the operation didn't document before
+    // is reported as that, rather than as a removal and an addition.
+    let primary = match (primary_success(old), primary_success(new)) {
+        (Some(from), Some(to))
+            if from != to && !old.responses.contains_key(&StatusCode::Code(to)) =>
+        {
+            Some((from, to))
+        }
+        _ => None,
+    };
+
+    let existing = old.responses.iter().flat_map(|(code, old_ref)| {
+        let resp_loc = loc.response(&status_code_str(code));
+        match new.responses.get(code) {
+            Some(new_ref) => diff_response(&resp_loc, old_ref, new_ref, cx, direction),
+            // A code folded into a range or `default` is still documented;
+            // compare it against the response that now covers it.
+            None => match fallback_response(new, code) {
+                Some((fallback, new_ref)) => {
+                    let covered =
+                        Event::new(&resp_loc, EventKind::ResponseCovered { by: fallback });
+                    std::iter::once(covered)
+                        .chain(diff_response(&resp_loc, old_ref, new_ref, cx, direction))
+                        .collect()
+                }
+                None if primary.is_some_and(|(from, _)| *code == StatusCode::Code(from)) => {
+                    vec![]
+                }
+                None => vec![Event::new(
+                    &resp_loc,
+                    EventKind::ResponseRemoved {
+                        code: status_code_str(code),
+                    },
+                )],
+            },
+        }
+    });
+
     let added = new.responses.keys().filter_map(|code| {
-        if old.responses.contains_key(code) {
+        if old.responses.contains_key(code)
+            || primary.is_some_and(|(_, to)| *code == StatusCode::Code(to))
+        {
             None
         } else {
-            Some(Change {
-                severity: Severity::NonBreaking,
-                location: loc.clone(),
-                message: format!("response '{}' added", status_code_str(code)),
-            })
+            let code = status_code_str(code);
+            Some(Event::new(
+                &loc.response(&code),
+                EventKind::ResponseAdded { code },
+            ))
         }
     });
 
-    existing.chain(added).collect()
+    let default_loc = &loc.response("default");
+    let default = match (&old.default, &new.default) {
+        (Some(_), None) => vec![Event::new(
+            default_loc,
+            EventKind::ResponseRemoved {
+                code: "default".into(),
+            },
+        )],
+        (None, Some(_)) => vec![Event::new(
+            default_loc,
+            EventKind::ResponseAdded {
+                code: "default".into(),
+            },
+        )],
+        (Some(old_ref), Some(new_ref)) => {
+            diff_response(default_loc, old_ref, new_ref, cx, direction)
+        }
+        (None, None) => vec![],
+    };
+
+    let primary =
+        primary.map(|(from, to)| Event::new(loc, EventKind::PrimarySuccessChanged { from, to }));
+
+    existing
+        .chain(added)
+        .chain(default)
+        .chain(primary)
+        .collect()
 }
 
 // ---------------------------------------------------------------------------
 // Content (shared between request body and responses)
 // ---------------------------------------------------------------------------
 
-use indexmap::IndexMap;
 use openapiv3::MediaType;
 
-#[derive(Debug, Clone, Copy)]
-enum Direction {
+/// Which way data flows: in a request to the API's provider, or in a
+/// response from it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    /// Sent by the client, read by the server.
     Request,
+    /// Sent by the server, read by the client.
     Response,
 }
 
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Direction::Request => write!(f, "request"),
+            Direction::Response => write!(f, "response"),
+        }
+    }
+}
+
+impl Direction {
+    fn reverse(self) -> Self {
+        match self {
+            Direction::Request => Direction::Response,
+            Direction::Response => Direction::Request,
+        }
+    }
+}
+
 fn diff_content(
     loc: &Location,
-    label: &str,
     old_content: &IndexMap<String, MediaType>,
     new_content: &IndexMap<String, MediaType>,
     direction: Direction,
-    old_spec: &OpenAPI,
-    new_spec: &OpenAPI,
-) -> Vec<Change> {
-    let existing = old_content
+    cx: &Ctx,
+) -> Vec<Event> {
+    let old_ranges: Vec<MediaRange> = old_content.keys().map(|m| MediaRange::parse(m)).collect();
+    let new_ranges: Vec<MediaRange> = new_content.keys().map(|m| MediaRange::parse(m)).collect();
+
+    // An old media type is still served if the new spec lists it (ignoring
+    // parameters and case) or, for requests, a range accepting it, or, for
+    // responses, a narrower type within it.
+    let counterparts: Vec<Option<usize>> = old_ranges
         .iter()
-        .flat_map(|(media, old_mt)| match new_content.get(media) {
-            None => {
-                vec![Change {
-                    severity: Severity::Breaking,
-                    location: loc.clone(),
-                    message: format!("{label}: media type '{media}' removed"),
-                }]
-            }
-            Some(new_mt) => {
-                let old_schema = old_mt
-                    .schema
-                    .as_ref()
-                    .and_then(|r| resolve_schema(r, &old_spec.components));
-                let new_schema = new_mt
-                    .schema
-                    .as_ref()
-                    .and_then(|r| resolve_schema(r, &new_spec.components));
+        .map(|old| {
+            new_ranges.iter().position(|new| new == old).or_else(|| {
+                new_ranges.iter().position(|new| match direction {
+                    Direction::Request => new.covers(old),
+                    Direction::Response => old.covers(new),
+                })
+            })
+        })
+        .collect();
 
-                match (old_schema, new_schema) {
-                    (Some(old_s), Some(new_s)) => {
-                        let ctx = format!("{label} {media}");
-                        diff_schema(
-                            loc,
-                            &ctx,
-                            old_s,
-                            new_s,
-                            direction,
-                            &old_spec.components,
-                            &new_spec.components,
-                            0,
-                        )
+    // A media type that disappeared may have been replaced by a new one with
+    // the same structured syntax, e.g. `application/json` by
+    // `application/problem+json`; compare their schemas as well.
+    let replacements: Vec<Option<usize>> = old_ranges
+        .iter()
+        .zip(&counterparts)
+        .map(|(old, counterpart)| match counterpart {
+            Some(_) => None,
+            None => (0..new_ranges.len()).find(|&i| {
+                !counterparts.contains(&Some(i)) && new_ranges[i].syntax() == old.syntax()
+            }),
+        })
+        .collect();
+
+    let any_served = counterparts.iter().any(Option::is_some);
+
+    let existing = old_content
+        .iter()
+        .zip(&old_ranges)
+        .zip(counterparts.iter().zip(&replacements))
+        .flat_map(|(((media, old_mt), old_range), pairing)| {
+            let paired = match pairing {
+                (Some(i), _) | (None, Some(i)) => new_content.get_index(*i),
+                (None, None) => None,
+            };
+            // Changes to a pair whose keys are written differently keep the
+            // new key too, so the new side can be looked up.
+            let media_loc = &match paired {
+                Some((new_media, _)) if new_media != media => Location::MediaType {
+                    parent: Box::new(loc.clone()),
+                    media: media.clone(),
+                    new_media: Some(new_media.clone()),
+                },
+                _ => loc.media_type(media),
+            };
+            let compare = |new_mt: &MediaType| match (&old_mt.schema, &new_mt.schema) {
+                (Some(old_ref), Some(new_ref))
+                    if defer_component(cx, media_loc, old_ref, new_ref, direction) =>
+                {
+                    vec![]
+                }
+                (Some(ReferenceOr::Item(old_s)), Some(ReferenceOr::Item(new_s))) => {
+                    diff_deprecated(
+                        media_loc,
+                        "schema",
+                        old_s.schema_data.deprecated,
+                        new_s.schema_data.deprecated,
+                    )
+                    .into_iter()
+                    .chain(diff_schema(media_loc, old_s, new_s, direction, cx, &[]))
+                    .collect()
+                }
+                (Some(old_ref), Some(new_ref)) => {
+                    let old_schema = resolve_schema(old_ref, &cx.old.components);
+                    let new_schema = resolve_schema(new_ref, &cx.new.components);
+                    let visited = enter_refs(&[], schema_ref(old_ref), schema_ref(new_ref));
+                    match (old_schema, new_schema, visited) {
+                        (Some(old_s), Some(new_s), Some(visited)) => {
+                            diff_schema(media_loc, old_s, new_s, direction, cx, &visited)
+                        }
+                        _ => vec![],
                     }
-                    _ => vec![],
                 }
-            }
+                _ => vec![],
+            };
+
+            let Some((new_media, new_mt)) = paired else {
+                let kind = EventKind::MediaTypeRemoved {
+                    media: media.clone(),
+                    alternatives: any_served,
+                };
+                return vec![Event::new(media_loc, kind).flowing(direction)];
+            };
+            let kind = match pairing {
+                (Some(i), _) if new_ranges[*i] == *old_range => None,
+                (Some(_), _) => Some(EventKind::MediaTypeCovered {
+                    media: media.clone(),
+                    by: new_media.clone(),
+                }),
+                _ => Some(EventKind::MediaTypeReplaced {
+                    media: media.clone(),
+                    by: new_media.clone(),
+                }),
+            };
+            let example = diff_doc(
+                media_loc,
+                "example",
+                old_mt.example.as_ref(),
+                new_mt.example.as_ref(),
+            );
+            let named_examples = diff_doc(
+                media_loc,
+                "examples",
+                examples(&old_mt.examples),
+                examples(&new_mt.examples),
+            );
+            kind.map(|kind| Event::new(media_loc, kind).flowing(direction))
+                .into_iter()
+                .chain(compare(new_mt))
+                .chain(example)
+                .chain(named_examples)
+                .collect()
         });
 
-    let added = new_content.keys().filter_map(|media| {
-        if old_content.contains_key(media) {
+    let added = new_content.keys().enumerate().filter_map(|(i, media)| {
+        if counterparts.contains(&Some(i)) || replacements.contains(&Some(i)) {
             None
         } else {
-            Some(Change {
-                severity: Severity::NonBreaking,
-                location: loc.clone(),
-                message: format!("{label}: media type '{media}' added"),
-            })
+            let kind = EventKind::MediaTypeAdded {
+                media: media.clone(),
+            };
+            Some(Event::new(&loc.media_type(media), kind).flowing(direction))
         }
     });
 
@@ -595,7 +1680,32 @@ fn diff_content(
 // Layer 7: Schema comparison
 // ---------------------------------------------------------------------------
 
-const MAX_DEPTH: usize = 10;
+/// The `$ref`s an old/new schema pair was reached through (empty when inline).
+type RefPair = (String, String);
+
+fn schema_ref<T>(r: &ReferenceOr<T>) -> &str {
+    match r {
+        ReferenceOr::Reference { reference } => reference,
+        ReferenceOr::Item(_) => "",
+    }
+}
+
+/// Extend the chain of `$ref` pairs currently being compared. Returns `None`
+/// when the pair is already on the chain: the schemas are recursive and this
+/// level is being compared further up, so descending again would only repeat
+/// the same changes.
+fn enter_refs(visited: &[RefPair], old: &str, new: &str) -> Option<Vec<RefPair>> {
+    let mut chain = visited.to_vec();
+    if old.is_empty() && new.is_empty() {
+        return Some(chain);
+    }
+    let pair = (old.to_string(), new.to_string());
+    if chain.contains(&pair) {
+        return None;
+    }
+    chain.push(pair);
+    Some(chain)
+}
 
 fn resolve_schema<'a>(
     r: &'a ReferenceOr<Schema>,
@@ -604,8 +1714,7 @@ fn resolve_schema<'a>(
     match r {
         ReferenceOr::Item(s) => Some(s),
         ReferenceOr::Reference { reference } => {
-            let prefix = "#/components/schemas/";
-            reference.strip_prefix(prefix).and_then(|name| {
+            reference.strip_prefix(SCHEMA_PREFIX).and_then(|name| {
                 components
                     .as_ref()
                     .and_then(|c| c.schemas.get(name))
@@ -622,8 +1731,7 @@ fn resolve_box_schema<'a>(
     match r {
         ReferenceOr::Item(s) => Some(s.as_ref()),
         ReferenceOr::Reference { reference } => {
-            let prefix = "#/components/schemas/";
-            reference.strip_prefix(prefix).and_then(|name| {
+            reference.strip_prefix(SCHEMA_PREFIX).and_then(|name| {
                 components
                     .as_ref()
                     .and_then(|c| c.schemas.get(name))
@@ -653,98 +1761,273 @@ fn type_name(kind: &SchemaKind) -> String {
 
 fn diff_schema(
     loc: &Location,
-    context: &str,
     old: &Schema,
     new: &Schema,
     direction: Direction,
-    old_components: &Option<Components>,
-    new_components: &Option<Components>,
-    depth: usize,
-) -> Vec<Change> {
-    if depth >= MAX_DEPTH {
-        return vec![];
-    }
-
+    cx: &Ctx,
+    visited: &[RefPair],
+) -> Vec<Event> {
     let old_type = type_name(&old.schema_kind);
     let new_type = type_name(&new.schema_kind);
 
     if old_type != new_type {
-        return vec![Change {
-            severity: Severity::Breaking,
-            location: loc.clone(),
-            message: format!("{context}: type changed from {old_type} to {new_type}"),
-        }];
+        let kind = EventKind::TypeChanged {
+            from: old_type,
+            to: new_type,
+        };
+        return vec![Event::new(loc, kind).flowing(direction)];
     }
 
-    match (&old.schema_kind, &new.schema_kind) {
+    let description = diff_doc(
+        loc,
+        "description",
+        old.schema_data.description.as_ref(),
+        new.schema_data.description.as_ref(),
+    );
+    let example = diff_doc(
+        loc,
+        "example",
+        old.schema_data.example.as_ref(),
+        new.schema_data.example.as_ref(),
+    );
+
+    let changes = match (&old.schema_kind, &new.schema_kind) {
         (SchemaKind::Type(Type::Object(old_obj)), SchemaKind::Type(Type::Object(new_obj))) => {
-            diff_object(
-                loc,
-                context,
-                old_obj,
-                new_obj,
-                direction,
-                old_components,
-                new_components,
-                depth,
-            )
+            diff_object(loc, old_obj, new_obj, direction, cx, visited)
         }
         (SchemaKind::Type(Type::Array(old_arr)), SchemaKind::Type(Type::Array(new_arr))) => {
-            let old_items = old_arr
-                .items
-                .as_ref()
-                .and_then(|r| resolve_box_schema(r, old_components));
-            let new_items = new_arr
-                .items
-                .as_ref()
-                .and_then(|r| resolve_box_schema(r, new_components));
-            match (old_items, new_items) {
-                (Some(old_s), Some(new_s)) => diff_schema(
-                    loc,
-                    &format!("{context}[]"),
-                    old_s,
-                    new_s,
-                    direction,
-                    old_components,
-                    new_components,
-                    depth + 1,
-                ),
+            match (&old_arr.items, &new_arr.items) {
+                (Some(old_ref), Some(new_ref)) => {
+                    diff_schema_ref(&loc.items(), old_ref, new_ref, direction, cx, visited)
+                }
                 _ => vec![],
             }
         }
         (SchemaKind::Type(Type::String(old_s)), SchemaKind::Type(Type::String(new_s))) => {
-            diff_string_enum(loc, context, old_s, new_s, direction)
+            diff_string_enum(loc, old_s, new_s, direction)
+        }
+        _ => vec![],
+    };
+    changes
+        .into_iter()
+        .chain(description)
+        .chain(example)
+        .collect()
+}
+
+/// Resolve and compare a nested schema pair, stopping at reference cycles.
+fn diff_schema_ref(
+    loc: &Location,
+    old: &ReferenceOr<Box<Schema>>,
+    new: &ReferenceOr<Box<Schema>>,
+    direction: Direction,
+    cx: &Ctx,
+    visited: &[RefPair],
+) -> Vec<Event> {
+    if defer_component(cx, loc, old, new, direction) {
+        return vec![];
+    }
+    let old_schema = resolve_box_schema(old, &cx.old.components);
+    let new_schema = resolve_box_schema(new, &cx.new.components);
+    match (
+        old_schema,
+        new_schema,
+        enter_refs(visited, schema_ref(old), schema_ref(new)),
+    ) {
+        (Some(old_s), Some(new_s), Some(visited)) => {
+            diff_schema(loc, old_s, new_s, direction, cx, &visited)
         }
         _ => vec![],
     }
 }
 
+// ---------------------------------------------------------------------------
+// Shared components (grouped mode)
+// ---------------------------------------------------------------------------
+
+const SCHEMA_PREFIX: &str = "#/components/schemas/";
+
+/// In grouped mode, a schema reached through the same component `$ref` in
+/// both specs is diffed once by `diff_components`; record the use site
+/// instead of descending into it here.
+fn defer_component<T>(
+    cx: &Ctx,
+    loc: &Location,
+    old: &ReferenceOr<T>,
+    new: &ReferenceOr<T>,
+    direction: Direction,
+) -> bool {
+    if !cx.options.group_components || schema_ref(old) != schema_ref(new) {
+        return false;
+    }
+    let Some(name) = schema_ref(old).strip_prefix(SCHEMA_PREFIX) else {
+        return false;
+    };
+    let scope = loc.scope();
+    if *scope != Location::Component(name.to_string()) {
+        cx.usages.borrow_mut().push(Usage {
+            component: name.to_string(),
+            location: scope.clone(),
+            direction,
+        });
+    }
+    true
+}
+
+/// Report schemas under `components/schemas` that became deprecated or are
+/// no longer deprecated, regardless of where they are used.
+fn diff_component_deprecations(cx: &Ctx) -> Vec<Event> {
+    cx.old
+        .components
+        .iter()
+        .flat_map(|c| c.schemas.iter())
+        .filter_map(|(name, old_ref)| {
+            let new_ref = cx.new.components.as_ref()?.schemas.get(name)?;
+            diff_deprecated(
+                &Location::Component(name.clone()),
+                "schema",
+                old_ref.as_item()?.schema_data.deprecated,
+                new_ref.as_item()?.schema_data.deprecated,
+            )
+        })
+        .collect()
+}
+
+/// Diff every component recorded by `defer_component`, once per direction it
+/// is used in, and classify the events of each. Diffing a component may
+/// record further components it refers to, so keep going until no new ones
+/// turn up.
+fn diff_components(cx: &Ctx) -> Vec<Change> {
+    let mut diffed: Vec<((String, Direction), Vec<Change>)> = Vec::new();
+
+    loop {
+        let next = cx
+            .usages
+            .borrow()
+            .iter()
+            .map(|u| (u.component.clone(), u.direction))
+            .find(|key| diffed.iter().all(|(done, _)| done != key));
+        let Some((name, direction)) = next else {
+            break;
+        };
+        let changes = rules::classify(cx, &diff_component(cx, &name, direction));
+        diffed.push(((name, direction), changes));
+    }
+
+    let usages = cx.usages.borrow();
+    let mut names: Vec<&str> = Vec::new();
+    for ((name, _), _) in &diffed {
+        if !names.contains(&name.as_str()) {
+            names.push(name);
+        }
+    }
+
+    names
+        .into_iter()
+        .flat_map(|name| {
+            let sides: Vec<(Direction, &[Change])> = diffed
+                .iter()
+                .filter(|((n, _), _)| n == name)
+                .map(|((_, direction), changes)| (*direction, changes.as_slice()))
+                .collect();
+            merge_directions(name, &sides, &usages)
+        })
+        .collect()
+}
+
+/// Combine the diffs of a component used in several directions: each change
+/// gets the worst severity any direction gives it. A change that is only
+/// breaking in some directions says which use sites make it so.
+fn merge_directions(name: &str, sides: &[(Direction, &[Change])], usages: &[Usage]) -> Vec<Change> {
+    let mut merged: Vec<(Change, Vec<Direction>)> = Vec::new();
+
+    for (direction, changes) in sides {
+        for change in *changes {
+            let same = |c: &Change| c.location == change.location && c.message == change.message;
+            match merged.iter_mut().find(|(c, _)| same(c)) {
+                None => merged.push((change.clone(), vec![*direction])),
+                Some((worst, directions)) => {
+                    if change.severity < worst.severity {
+                        *worst = change.clone();
+                        *directions = vec![*direction];
+                    } else if change.severity == worst.severity {
+                        directions.push(*direction);
+                    }
+                }
+            }
+        }
+    }
+
+    merged
+        .into_iter()
+        .map(|(mut change, directions)| {
+            if sides.len() > 1
+                && directions.len() < sides.len()
+                && change.severity == Severity::Breaking
+            {
+                let direction = directions[0];
+                let ops: Vec<String> = affected_usages(usages, name, Some(direction))
+                    .iter()
+                    .map(|u| u.location.to_string())
+                    .collect();
+                change.message = format!(
+                    "{} (breaking because the schema is also used in a {direction} for {})",
+                    change.message,
+                    ops.join(", ")
+                );
+            }
+            change
+        })
+        .collect()
+}
+
+fn diff_component(cx: &Ctx, name: &str, direction: Direction) -> Vec<Event> {
+    let reference = format!("{SCHEMA_PREFIX}{name}");
+    let component = ReferenceOr::Reference {
+        reference: reference.clone(),
+    };
+    match (
+        resolve_schema(&component, &cx.old.components),
+        resolve_schema(&component, &cx.new.components),
+    ) {
+        (Some(old_s), Some(new_s)) => diff_schema(
+            &Location::Component(name.to_string()),
+            old_s,
+            new_s,
+            direction,
+            cx,
+            &[(reference.clone(), reference)],
+        ),
+        _ => vec![],
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Object property comparison
 // ---------------------------------------------------------------------------
 
 fn diff_object(
     loc: &Location,
-    context: &str,
     old: &ObjectType,
     new: &ObjectType,
     direction: Direction,
-    old_components: &Option<Components>,
-    new_components: &Option<Components>,
-    depth: usize,
-) -> Vec<Change> {
-    let removed = old.properties.keys().filter_map(|prop_name| {
+    cx: &Ctx,
+    visited: &[RefPair],
+) -> Vec<Event> {
+    let removed = old.properties.iter().filter_map(|(prop_name, old_ref)| {
         if new.properties.contains_key(prop_name) {
             None
         } else {
-            let sev = match direction {
-                Direction::Response => Severity::Breaking,
-                Direction::Request => Severity::NonBreaking,
+            let kind = EventKind::PropertyRemoved {
+                name: prop_name.clone(),
             };
-            Some(Change {
-                severity: sev,
-                location: loc.clone(),
-                message: format!("{context}: property '{prop_name}' removed"),
+            let event = Event::new(&loc.property(prop_name), kind).flowing(direction);
+            Some(match resolve_box_schema(old_ref, &cx.old.components) {
+                Some(schema) => event.retiring(
+                    schema.schema_data.deprecated,
+                    &schema.schema_data.extensions,
+                ),
+                None => event,
             })
         }
     });
@@ -753,30 +2036,20 @@ fn diff_object(
         if old.properties.contains_key(prop_name) {
             None
         } else {
-            let is_required = new.required.contains(prop_name);
-            let sev = match direction {
-                Direction::Request if is_required => Severity::Breaking,
-                _ => Severity::NonBreaking,
+            let kind = EventKind::PropertyAdded {
+                name: prop_name.clone(),
+                required: new.required.contains(prop_name),
             };
-            Some(Change {
-                severity: sev,
-                location: loc.clone(),
-                message: format!("{context}: property '{prop_name}' added"),
-            })
+            Some(Event::new(&loc.property(prop_name), kind).flowing(direction))
         }
     });
 
     let became_required = new.required.iter().filter_map(|prop_name| {
         if !old.required.contains(prop_name) && old.properties.contains_key(prop_name) {
-            let sev = match direction {
-                Direction::Request => Severity::Breaking,
-                Direction::Response => Severity::NonBreaking,
+            let kind = EventKind::PropertyBecameRequired {
+                name: prop_name.clone(),
             };
-            Some(Change {
-                severity: sev,
-                location: loc.clone(),
-                message: format!("{context}: property '{prop_name}' became required"),
-            })
+            Some(Event::new(&loc.property(prop_name), kind).flowing(direction))
         } else {
             None
         }
@@ -784,40 +2057,42 @@ fn diff_object(
 
     let became_optional = old.required.iter().filter_map(|prop_name| {
         if !new.required.contains(prop_name) && new.properties.contains_key(prop_name) {
-            let sev = match direction {
-                Direction::Request => Severity::NonBreaking,
-                Direction::Response => Severity::Breaking,
+            let kind = EventKind::PropertyBecameOptional {
+                name: prop_name.clone(),
             };
-            Some(Change {
-                severity: sev,
-                location: loc.clone(),
-                message: format!("{context}: property '{prop_name}' became optional"),
-            })
+            Some(Event::new(&loc.property(prop_name), kind).flowing(direction))
         } else {
             None
         }
     });
 
+    // A `$ref`'d property's deprecation belongs to the component it points
+    // at, which `diff_component_deprecations` reports.
+    let deprecated = old.properties.iter().filter_map(|(prop_name, old_ref)| {
+        match (old_ref, new.properties.get(prop_name)?) {
+            (ReferenceOr::Item(old_s), ReferenceOr::Item(new_s)) => diff_deprecated(
+                &loc.property(prop_name),
+                "",
+                old_s.schema_data.deprecated,
+                new_s.schema_data.deprecated,
+            ),
+            _ => None,
+        }
+    });
+
     let recursed = old.properties.iter().flat_map(|(prop_name, old_ref)| {
         new.properties
             .get(prop_name)
             .into_iter()
             .flat_map(move |new_ref| {
-                let old_schema = resolve_box_schema(old_ref, old_components);
-                let new_schema = resolve_box_schema(new_ref, new_components);
-                match (old_schema, new_schema) {
-                    (Some(old_s), Some(new_s)) => diff_schema(
-                        loc,
-                        &format!("{context}.{prop_name}"),
-                        old_s,
-                        new_s,
-                        direction,
-                        old_components,
-                        new_components,
-                        depth + 1,
-                    ),
-                    _ => vec![],
-                }
+                diff_schema_ref(
+                    &loc.property(prop_name),
+                    old_ref,
+                    new_ref,
+                    direction,
+                    cx,
+                    visited,
+                )
             })
     });
 
@@ -825,6 +2100,7 @@ fn diff_object(
         .chain(added)
         .chain(became_required)
         .chain(became_optional)
+        .chain(deprecated)
         .chain(recursed)
         .collect()
 }
@@ -835,29 +2111,25 @@ fn diff_object(
 
 fn diff_string_enum(
     loc: &Location,
-    context: &str,
     old: &StringType,
     new: &StringType,
     direction: Direction,
-) -> Vec<Change> {
+) -> Vec<Event> {
     if old.enumeration.is_empty() && new.enumeration.is_empty() {
         return vec![];
     }
 
-    let old_values: std::collections::HashSet<_> =
-        old.enumeration.iter().filter_map(|v| v.as_ref()).collect();
-    let new_values: std::collections::HashSet<_> =
-        new.enumeration.iter().filter_map(|v| v.as_ref()).collect();
+    let old_values: IndexSet<_> = old.enumeration.iter().filter_map(|v| v.as_ref()).collect();
+    let new_values: IndexSet<_> = new.enumeration.iter().filter_map(|v| v.as_ref()).collect();
 
     let removed = old_values.iter().filter_map(|val| {
         if new_values.contains(val) {
             None
         } else {
-            Some(Change {
-                severity: Severity::Breaking,
-                location: loc.clone(),
-                message: format!("{context}: enum value '{val}' removed"),
-            })
+            let kind = EventKind::EnumValueRemoved {
+                value: val.to_string(),
+            };
+            Some(Event::new(loc, kind).flowing(direction))
         }
     });
 
@@ -865,15 +2137,10 @@ fn diff_string_enum(
         if old_values.contains(val) {
             None
         } else {
-            let sev = match direction {
-                Direction::Response => Severity::Breaking,
-                Direction::Request => Severity::NonBreaking,
+            let kind = EventKind::EnumValueAdded {
+                value: val.to_string(),
             };
-            Some(Change {
-                severity: sev,
-                location: loc.clone(),
-                message: format!("{context}: enum value '{val}' added"),
-            })
+            Some(Event::new(loc, kind).flowing(direction))
         }
     });
 
@@ -0,0 +1,279 @@
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use super::Direction;
+use super::stability::STABLE;
+use crate::change::Location;
+
+/// Something that differs between the two specs, as found by the walker and
+/// before any rule has judged it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Event {
+    /// What changed.
+    #[serde(flatten)]
+    pub kind: EventKind,
+    /// Where it changed: the element itself, also for additions and
+    /// removals. Elements without a location of their own, such as
+    /// callbacks, security requirements, tags and enum values, are located
+    /// at what holds them.
+    pub location: Location,
+    /// Which way the affected data flows, where the rules depend on it:
+    /// parameters, request bodies, headers, media types and schemas.
+    pub direction: Option<Direction>,
+    /// For removals: whether the removed element was deprecated in the old
+    /// spec.
+    pub deprecated: bool,
+    /// For removals: the removed element's `x-sunset` date, if any.
+    pub sunset: Option<String>,
+}
+
+impl Event {
+    pub(super) fn new(location: &Location, kind: EventKind) -> Self {
+        Event {
+            kind,
+            location: location.clone(),
+            direction: None,
+            deprecated: false,
+            sunset: None,
+        }
+    }
+
+    pub(super) fn flowing(mut self, direction: Direction) -> Self {
+        self.direction = Some(direction);
+        self
+    }
+
+    /// Record the deprecation state of a removed element.
+    pub(super) fn retiring(
+        mut self,
+        deprecated: bool,
+        extensions: &IndexMap<String, serde_json::Value>,
+    ) -> Self {
+        self.deprecated = deprecated;
+        self.sunset = extensions
+            .get("x-sunset")
+            .and_then(|v| v.as_str())
+            .map(str::to_string);
+        self
+    }
+}
+
+/// The kinds of differences the walker reports. Displaying a kind gives the
+/// message of the resulting change, relative to the event's location.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind")]
+#[non_exhaustive]
+#[allow(missing_docs)] // Variant fields are named after what they hold.
+pub enum EventKind {
+    /// A server URL was removed.
+    ServerRemoved { url: String },
+    /// A server URL was added.
+    ServerAdded { url: String },
+    /// A server variable was removed.
+    ServerVariableRemoved { name: String },
+    /// A server variable was added.
+    ServerVariableAdded { name: String },
+    /// A server variable's default value changed.
+    ServerVariableDefaultChanged {
+        name: String,
+        from: String,
+        to: String,
+    },
+    /// A value was removed from a server variable's `enum`.
+    ServerVariableValueRemoved { name: String, value: String },
+    /// A value was added to a server variable's `enum`.
+    ServerVariableValueAdded { name: String, value: String },
+    /// The only base path an operation is served under changed.
+    BasePathChanged { from: String, to: String },
+    /// An operation is no longer served under a base path.
+    BasePathRemoved { path: String },
+    /// A path was removed.
+    PathRemoved,
+    /// A path was added.
+    PathAdded,
+    /// An operation was removed.
+    OperationRemoved,
+    /// An operation was added.
+    OperationAdded,
+    /// A callback was removed from an operation.
+    CallbackRemoved { name: String },
+    /// A callback was added to an operation.
+    CallbackAdded { name: String },
+    /// A parameter was removed.
+    ParameterRemoved {
+        name: String,
+        r#in: String,
+        required: bool,
+    },
+    /// A parameter was added.
+    ParameterAdded {
+        name: String,
+        r#in: String,
+        required: bool,
+    },
+    /// A request body was removed.
+    RequestBodyRemoved,
+    /// A request body was added.
+    RequestBodyAdded { required: bool },
+    /// A response was removed.
+    ResponseRemoved { code: String },
+    /// A response was added.
+    ResponseAdded { code: String },
+    /// A response is no longer listed, but a range or `default` covers it.
+    ResponseCovered { by: String },
+    /// The lowest 2xx response code changed.
+    PrimarySuccessChanged { from: u16, to: u16 },
+    /// A response header was removed.
+    HeaderRemoved { name: String },
+    /// A response header was added.
+    HeaderAdded { name: String, required: bool },
+    /// A media type was removed; `alternatives` tells whether any of the
+    /// old media types is still served.
+    MediaTypeRemoved { media: String, alternatives: bool },
+    /// A media type was added.
+    MediaTypeAdded { media: String },
+    /// A media type was replaced by a range or a narrower type matching it.
+    MediaTypeCovered { media: String, by: String },
+    /// A media type was replaced by another with the same structured syntax.
+    MediaTypeReplaced { media: String, by: String },
+    /// A parameter, header or request body became required.
+    BecameRequired,
+    /// A parameter, header or request body became optional.
+    BecameOptional,
+    /// The type of a parameter, header or schema changed.
+    TypeChanged { from: String, to: String },
+    /// An object property was removed.
+    PropertyRemoved { name: String },
+    /// An object property was added.
+    PropertyAdded { name: String, required: bool },
+    /// An existing object property became required.
+    PropertyBecameRequired { name: String },
+    /// An object property stopped being required.
+    PropertyBecameOptional { name: String },
+    /// A value was removed from a string `enum`.
+    EnumValueRemoved { value: String },
+    /// A value was added to a string `enum`.
+    EnumValueAdded { value: String },
+    /// A security requirement was removed.
+    /// `still_open` is set when the operation can now be called without
+    /// credentials, so no client needs the removed requirement.
+    SecurityRequirementRemoved {
+        requirement: String,
+        still_open: bool,
+    },
+    /// A security requirement was added. `newly_required` is set when the
+    /// operation could be called without credentials before, but not now.
+    SecurityRequirementAdded {
+        requirement: String,
+        newly_required: bool,
+    },
+    /// A security scheme requires an additional scope.
+    ScopeAdded { scope: String },
+    /// A security scheme no longer requires a scope.
+    ScopeRemoved { scope: String },
+    /// An element became deprecated; an empty subject is the element at the
+    /// event's location.
+    Deprecated { subject: String },
+    /// An element is no longer deprecated.
+    Undeprecated { subject: String },
+    /// An operation's `x-stability` level changed.
+    StabilityChanged { from: String, to: String },
+    /// A documentation field was added.
+    DocumentationAdded { field: String },
+    /// A documentation field was removed.
+    DocumentationRemoved { field: String },
+    /// A documentation field was edited.
+    DocumentationChanged { field: String },
+    /// A tag was added to an operation.
+    TagAdded { tag: String },
+    /// A tag was removed from an operation.
+    TagRemoved { tag: String },
+}
+
+impl fmt::Display for EventKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use EventKind::*;
+        match self {
+            ServerRemoved { .. } => write!(f, "removed"),
+            ServerAdded { .. } => write!(f, "added"),
+            ServerVariableRemoved { name } => write!(f, "variable '{name}' removed"),
+            ServerVariableAdded { name } => write!(f, "variable '{name}' added"),
+            ServerVariableDefaultChanged { name, from, to } => {
+                write!(
+                    f,
+                    "variable '{name}' default changed from '{from}' to '{to}'"
+                )
+            }
+            ServerVariableValueRemoved { name, value } => {
+                write!(f, "variable '{name}' enum value '{value}' removed")
+            }
+            ServerVariableValueAdded { name, value } => {
+                write!(f, "variable '{name}' enum value '{value}' added")
+            }
+            BasePathChanged { from, to } => {
+                write!(f, "base path changed from '{from}' to '{to}'")
+            }
+            BasePathRemoved { path } => write!(f, "base path '{path}' removed"),
+            PathRemoved => write!(f, "endpoint removed"),
+            PathAdded => write!(f, "endpoint added"),
+            OperationRemoved => write!(f, "operation removed"),
+            OperationAdded => write!(f, "operation added"),
+            CallbackRemoved { name } => write!(f, "callback '{name}' removed"),
+            CallbackAdded { name } => write!(f, "callback '{name}' added"),
+            ParameterRemoved { .. } => write!(f, "removed"),
+            ParameterAdded { .. } => write!(f, "added"),
+            RequestBodyRemoved => write!(f, "removed"),
+            RequestBodyAdded { .. } => write!(f, "added"),
+            ResponseRemoved { .. } => write!(f, "removed"),
+            ResponseAdded { .. } => write!(f, "added"),
+            ResponseCovered { by } => write!(f, "now covered by '{by}'"),
+            PrimarySuccessChanged { from, to } => {
+                write!(
+                    f,
+                    "primary success response changed from '{from}' to '{to}'"
+                )
+            }
+            HeaderRemoved { .. } => write!(f, "removed"),
+            HeaderAdded { .. } => write!(f, "added"),
+            MediaTypeRemoved { .. } => write!(f, "removed"),
+            MediaTypeAdded { .. } => write!(f, "added"),
+            MediaTypeCovered { by, .. } => write!(f, "covered by '{by}'"),
+            MediaTypeReplaced { by, .. } => write!(f, "replaced by '{by}'"),
+            BecameRequired => write!(f, "became required"),
+            BecameOptional => write!(f, "became optional"),
+            TypeChanged { from, to } => write!(f, "type changed from {from} to {to}"),
+            PropertyRemoved { .. } => write!(f, "removed"),
+            PropertyAdded { .. } => write!(f, "added"),
+            PropertyBecameRequired { .. } => write!(f, "became required"),
+            PropertyBecameOptional { .. } => write!(f, "became optional"),
+            EnumValueRemoved { value } => write!(f, "enum value '{value}' removed"),
+            EnumValueAdded { value } => write!(f, "enum value '{value}' added"),
+            SecurityRequirementRemoved { requirement, .. } => {
+                write!(f, "security requirement '{requirement}' removed")
+            }
+            SecurityRequirementAdded { requirement, .. } => {
+                write!(f, "security requirement '{requirement}' added")
+            }
+            ScopeAdded { scope } => write!(f, "scope '{scope}' added"),
+            ScopeRemoved { scope } => write!(f, "scope '{scope}' removed"),
+            Deprecated { subject } if subject.is_empty() => write!(f, "deprecated"),
+            Deprecated { subject } => write!(f, "{subject} deprecated"),
+            Undeprecated { subject } if subject.is_empty() => write!(f, "no longer deprecated"),
+            Undeprecated { subject } => write!(f, "{subject} no longer deprecated"),
+            StabilityChanged { from, to } if to == STABLE => {
+                write!(f, "promoted from {from} to stable")
+            }
+            StabilityChanged { from, to } if from == STABLE => {
+                write!(f, "demoted from stable to {to}")
+            }
+            StabilityChanged { from, to } => write!(f, "stability changed from {from} to {to}"),
+            DocumentationAdded { field } => write!(f, "{field} added"),
+            DocumentationRemoved { field } => write!(f, "{field} removed"),
+            DocumentationChanged { field } => write!(f, "{field} changed"),
+            TagAdded { tag } => write!(f, "tag '{tag}' added"),
+            TagRemoved { tag } => write!(f, "tag '{tag}' removed"),
+        }
+    }
+}
@@ -0,0 +1,52 @@
+use std::fmt;
+
+/// A media type or media range reduced to its essence: lowercase, without
+/// parameters such as `; charset=utf-8`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct MediaRange {
+    kind: String,
+    subtype: String,
+}
+
+impl MediaRange {
+    pub(super) fn parse(raw: &str) -> Self {
+        let essence = raw.split(';').next().unwrap_or_default().trim();
+        let essence = essence.to_ascii_lowercase();
+        let (kind, subtype) = essence.split_once('/').unwrap_or((&essence, "*"));
+        Self {
+            kind: kind.trim().to_string(),
+            subtype: subtype.trim().to_string(),
+        }
+    }
+
+    /// The structured syntax suffix, e.g. `json` for `application/vnd.pet+json`.
+    fn suffix(&self) -> Option<&str> {
+        self.subtype.rsplit_once('+').map(|(_, suffix)| suffix)
+    }
+
+    /// The underlying syntax: the suffix if there is one, the subtype otherwise,
+    /// so `application/json` and `application/problem+json` are both `json`.
+    pub(super) fn syntax(&self) -> &str {
+        self.suffix().unwrap_or(&self.subtype)
+    }
+
+    /// Whether every media type matched by `other` is also matched by `self`.
+    /// Understands `*/*`, `application/*` and `application/*+json`.
+    pub(super) fn covers(&self, other: &MediaRange) -> bool {
+        let kind = self.kind == "*" || self.kind == other.kind;
+        let subtype = match self.subtype.as_str() {
+            "*" => true,
+            s => match s.strip_prefix("*+") {
+                Some(suffix) => other.suffix() == Some(suffix),
+                None => s == other.subtype,
+            },
+        };
+        kind && subtype
+    }
+}
+
+impl fmt::Display for MediaRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.kind, self.subtype)
+    }
+}
@@ -0,0 +1,496 @@
+use openapiv3::OpenAPI;
+use std::any::Any;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use super::stability::STABLE;
+use super::{Ctx, DiffOptions, Direction, Event, EventKind};
+use crate::change::{Change, Severity};
+
+/// Judges how events affect existing clients.
+///
+/// A rule that doesn't recognise an event returns `None` and leaves it to
+/// the next rule in the registry. For example, to treat a parameter whose
+/// name only changed case as breaking, whichever way the removed one was
+/// classified:
+///
+/// ```
+/// use apidiff::diff::{Event, EventKind, RuleContext};
+/// use apidiff::{DiffOptions, Rule, Rules, Severity};
+///
+/// struct ParameterCase;
+///
+/// impl Rule for ParameterCase {
+///     fn name(&self) -> &str {
+///         "parameter-case"
+///     }
+///
+///     fn classify(&self, event: &Event, cx: &RuleContext) -> Option<Severity> {
+///         let (EventKind::ParameterRemoved { name, r#in, .. }
+///         | EventKind::ParameterAdded { name, r#in, .. }) = &event.kind
+///         else {
+///             return None;
+///         };
+///         let renamed = cx.events.iter().any(|other| match &other.kind {
+///             EventKind::ParameterRemoved { name: n, r#in: i, .. }
+///             | EventKind::ParameterAdded { name: n, r#in: i, .. } => {
+///                 other.location.parent() == event.location.parent()
+///                     && i == r#in
+///                     && n != name
+///                     && n.eq_ignore_ascii_case(name)
+///             }
+///             _ => false,
+///         });
+///         renamed.then_some(Severity::Breaking)
+///     }
+/// }
+///
+/// let options = DiffOptions::new().rules(Rules::default().with(ParameterCase));
+/// ```
+pub trait Rule: Send + Sync {
+    /// Name used to replace or disable the rule, in kebab-case.
+    fn name(&self) -> &str;
+
+    /// The severity of `event`, or `None` if this rule doesn't judge it.
+    fn classify(&self, event: &Event, cx: &RuleContext) -> Option<Severity>;
+
+    /// Further changes to report because of `event`, whichever rule
+    /// classifies it. None by default.
+    fn findings(&self, _event: &Event, _cx: &RuleContext) -> Vec<Change> {
+        Vec::new()
+    }
+}
+
+/// What a rule can look at besides the event itself.
+#[non_exhaustive]
+pub struct RuleContext<'a> {
+    /// The old spec.
+    pub old: &'a OpenAPI,
+    /// The new spec.
+    pub new: &'a OpenAPI,
+    /// All events found in the same walk: the whole spec, or one component
+    /// in one direction when components are grouped.
+    pub events: &'a [Event],
+    options: &'a DiffOptions,
+    /// The registry being consulted.
+    rules: &'a [Arc<dyn Rule>],
+    /// Index of the first rule after the one being consulted.
+    next: usize,
+    cache: &'a Cache,
+}
+
+/// What the rules made of the current event so far, so each rule is
+/// consulted once per event however many rules ask for their fallback.
+#[derive(Default)]
+struct Cache {
+    /// The first rule from an index on that classifies the event, with its
+    /// severity.
+    decisions: RefCell<HashMap<usize, Option<(usize, Severity)>>>,
+    /// Values rules computed for the event, by rule index plus one.
+    memos: RefCell<HashMap<usize, Box<dyn Any>>>,
+}
+
+impl<'a> RuleContext<'a> {
+    /// How the rules registered after the current one classify `event`:
+    /// the name of the deciding rule and its severity. Lets a rule adjust
+    /// the verdict it would otherwise get.
+    pub fn fallback(&self, event: &Event) -> Option<(&'a str, Severity)> {
+        let (i, severity) = self.decide(event, self.next)?;
+        Some((self.rules[i].name(), severity))
+    }
+
+    /// The value `compute` gives for the rule being consulted and the
+    /// current event, computed only the first time it is asked for, so a
+    /// rule can share work between `classify` and `findings`.
+    pub(super) fn memo<T: Clone + 'static>(&self, compute: impl FnOnce() -> T) -> T {
+        let cached = self.cache.memos.borrow().get(&self.next).map(|value| {
+            value
+                .downcast_ref::<T>()
+                .expect("a rule memoizes one type")
+                .clone()
+        });
+        if let Some(value) = cached {
+            return value;
+        }
+        let value = compute();
+        self.cache
+            .memos
+            .borrow_mut()
+            .insert(self.next, Box::new(value.clone()));
+        value
+    }
+
+    /// The first rule from index `from` on that classifies `event`, by
+    /// index, and its severity.
+    fn decide(&self, event: &Event, from: usize) -> Option<(usize, Severity)> {
+        let known = self.cache.decisions.borrow().get(&from).copied();
+        if let Some(decision) = known {
+            return decision;
+        }
+        let rule = self.rules.get(from)?;
+        let decision = match rule.classify(event, &self.consulting(from + 1)) {
+            Some(severity) => Some((from, severity)),
+            None => self.decide(event, from + 1),
+        };
+        self.cache.decisions.borrow_mut().insert(from, decision);
+        decision
+    }
+
+    fn findings(&self, event: &Event) -> Vec<Change> {
+        self.rules
+            .iter()
+            .enumerate()
+            .flat_map(|(i, rule)| rule.findings(event, &self.consulting(i + 1)))
+            .collect()
+    }
+
+    /// The context for the rule before index `next`.
+    fn consulting(&self, next: usize) -> RuleContext<'a> {
+        RuleContext { next, ..*self }
+    }
+
+    /// A context for consulting `rules` afresh.
+    fn over<'r>(&self, rules: &'r [Arc<dyn Rule>], cache: &'r Cache) -> RuleContext<'r>
+    where
+        'a: 'r,
+    {
+        RuleContext {
+            old: self.old,
+            new: self.new,
+            events: self.events,
+            options: self.options,
+            rules,
+            next: 0,
+            cache,
+        }
+    }
+}
+
+/// An ordered registry of rules. For each event, the first rule that
+/// classifies it decides its severity; events no rule classifies are not
+/// reported.
+///
+/// `Rules::default()` holds the built-in rules, named after what they
+/// cover: `servers`, `endpoints`, `parameters`, `request-body`,
+/// `responses`, `headers`, `media-types`, `required`, `types`,
+/// `properties`, `enums`, `security`, `deprecation`, `stability` and
+/// `documentation`.
+#[derive(Clone)]
+pub struct Rules {
+    rules: Vec<Arc<dyn Rule>>,
+}
+
+impl Rules {
+    /// A registry without any rules, reporting nothing.
+    pub fn empty() -> Self {
+        Rules { rules: Vec::new() }
+    }
+
+    /// Register `rule`, replacing any rule with the same name. It is
+    /// consulted before the rules already registered.
+    pub fn with(mut self, rule: impl Rule + 'static) -> Self {
+        self.rules.retain(|r| r.name() != rule.name());
+        self.rules.insert(0, Arc::new(rule));
+        self
+    }
+
+    /// Remove the rule called `name`, if registered.
+    pub fn without(mut self, name: &str) -> Self {
+        self.rules.retain(|r| r.name() != name);
+        self
+    }
+
+    /// Names of the registered rules, in the order they are consulted.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.rules.iter().map(|r| r.name())
+    }
+
+    /// The severity the first applicable rule gives `event`.
+    pub fn classify(&self, event: &Event, cx: &RuleContext) -> Option<Severity> {
+        let cache = Cache::default();
+        let cx = cx.over(&self.rules, &cache);
+        cx.decide(event, 0).map(|(_, severity)| severity)
+    }
+
+    /// The extra findings every rule reports for `event`.
+    pub fn findings(&self, event: &Event, cx: &RuleContext) -> Vec<Change> {
+        let cache = Cache::default();
+        cx.over(&self.rules, &cache).findings(event)
+    }
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Rules {
+            rules: BUILTIN
+                .iter()
+                .map(|&(name, classify)| Arc::new(Builtin { name, classify }) as Arc<dyn Rule>)
+                .collect(),
+        }
+    }
+}
+
+impl fmt::Debug for Rules {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.names()).finish()
+    }
+}
+
+/// Classify a batch of events from one walk into changes, applying the
+/// removal policy to removals of deprecated elements, and add the rules'
+/// extra findings.
+pub(super) fn classify(cx: &Ctx, events: &[Event]) -> Vec<Change> {
+    events
+        .iter()
+        .flat_map(|event| {
+            // Classification and findings share one cache, so each rule is
+            // consulted once for the event.
+            let cache = Cache::default();
+            let rule_cx = RuleContext {
+                old: cx.old,
+                new: cx.new,
+                events,
+                options: cx.options,
+                rules: &cx.options.rules.rules,
+                next: 0,
+                cache: &cache,
+            };
+            let decision = rule_cx.decide(event, 0);
+            let change = decision.map(|(_, severity)| {
+                let message = event.kind.to_string();
+                let (severity, message) = if severity == Severity::Breaking
+                    && cx.removal_allowed(event.deprecated, event.sunset.as_deref())
+                {
+                    (Severity::NonBreaking, format!("{message} (was deprecated)"))
+                } else {
+                    (severity, message)
+                };
+                Change::new(severity, event.location.clone(), message).with_event(event.clone())
+            });
+            change.into_iter().chain(rule_cx.findings(event))
+        })
+        .collect()
+}
+
+type Classify = fn(&Event, &RuleContext) -> Option<Severity>;
+
+struct Builtin {
+    name: &'static str,
+    classify: Classify,
+}
+
+impl Rule for Builtin {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn classify(&self, event: &Event, cx: &RuleContext) -> Option<Severity> {
+        (self.classify)(event, cx)
+    }
+}
+
+const BUILTIN: [(&str, Classify); 15] = [
+    ("servers", servers),
+    ("endpoints", endpoints),
+    ("parameters", parameters),
+    ("request-body", request_body),
+    ("responses", responses),
+    ("headers", headers),
+    ("media-types", media_types),
+    ("required", required),
+    ("types", types),
+    ("properties", properties),
+    ("enums", enums),
+    ("security", security),
+    ("deprecation", deprecation),
+    ("stability", stability),
+    ("documentation", documentation),
+];
+
+use EventKind::*;
+use Severity::{Breaking, Info, NonBreaking, Unstable, Warning};
+
+/// Severity of a field becoming mandatory: senders of a request must now
+/// provide it, while receivers of a response only gain a guarantee.
+fn now_required(event: &Event) -> Severity {
+    match event.direction {
+        Some(Direction::Response) => NonBreaking,
+        _ => Breaking,
+    }
+}
+
+fn now_optional(event: &Event) -> Severity {
+    match event.direction {
+        Some(Direction::Response) => Breaking,
+        _ => NonBreaking,
+    }
+}
+
+fn servers(event: &Event, _: &RuleContext) -> Option<Severity> {
+    match event.kind {
+        ServerRemoved { .. }
+        | ServerVariableRemoved { .. }
+        | ServerVariableDefaultChanged { .. }
+        | ServerVariableValueRemoved { .. }
+        | BasePathChanged { .. }
+        | BasePathRemoved { .. } => Some(Breaking),
+        ServerAdded { .. } | ServerVariableAdded { .. } | ServerVariableValueAdded { .. } => {
+            Some(NonBreaking)
+        }
+        _ => None,
+    }
+}
+
+fn endpoints(event: &Event, _: &RuleContext) -> Option<Severity> {
+    match event.kind {
+        PathRemoved | OperationRemoved | CallbackRemoved { .. } => Some(Breaking),
+        PathAdded | OperationAdded | CallbackAdded { .. } => Some(NonBreaking),
+        _ => None,
+    }
+}
+
+fn parameters(event: &Event, _: &RuleContext) -> Option<Severity> {
+    match event.kind {
+        ParameterRemoved { required: true, .. } => Some(Breaking),
+        ParameterRemoved {
+            required: false, ..
+        } => Some(NonBreaking),
+        ParameterAdded { required: true, .. } => Some(now_required(event)),
+        ParameterAdded {
+            required: false, ..
+        } => Some(NonBreaking),
+        _ => None,
+    }
+}
+
+fn request_body(event: &Event, _: &RuleContext) -> Option<Severity> {
+    match event.kind {
+        RequestBodyRemoved => Some(Breaking),
+        RequestBodyAdded { required: true } => Some(now_required(event)),
+        RequestBodyAdded { required: false } => Some(NonBreaking),
+        _ => None,
+    }
+}
+
+fn responses(event: &Event, _: &RuleContext) -> Option<Severity> {
+    match event.kind {
+        ResponseRemoved { .. } | PrimarySuccessChanged { .. } => Some(Breaking),
+        ResponseAdded { .. } | ResponseCovered { .. } => Some(NonBreaking),
+        _ => None,
+    }
+}
+
+/// Response headers are read by whoever receives the response, so they
+/// follow the same rules as response properties.
+fn headers(event: &Event, _: &RuleContext) -> Option<Severity> {
+    match event.kind {
+        HeaderRemoved { .. } => Some(now_optional(event)),
+        HeaderAdded { required: true, .. } => Some(now_required(event)),
+        HeaderAdded {
+            required: false, ..
+        } => Some(NonBreaking),
+        _ => None,
+    }
+}
+
+/// Clients negotiate response types with `Accept`, so losing one
+/// alternative only matters if none of the others remain.
+fn media_types(event: &Event, _: &RuleContext) -> Option<Severity> {
+    match (&event.kind, event.direction) {
+        (MediaTypeRemoved { alternatives, .. }, Some(Direction::Response)) if *alternatives => {
+            Some(NonBreaking)
+        }
+        (MediaTypeRemoved { .. } | MediaTypeReplaced { .. }, _) => Some(Breaking),
+        (MediaTypeAdded { .. } | MediaTypeCovered { .. }, _) => Some(NonBreaking),
+        _ => None,
+    }
+}
+
+fn required(event: &Event, _: &RuleContext) -> Option<Severity> {
+    match event.kind {
+        BecameRequired | PropertyBecameRequired { .. } => Some(now_required(event)),
+        BecameOptional | PropertyBecameOptional { .. } => Some(now_optional(event)),
+        _ => None,
+    }
+}
+
+fn types(event: &Event, _: &RuleContext) -> Option<Severity> {
+    match event.kind {
+        TypeChanged { .. } => Some(Breaking),
+        _ => None,
+    }
+}
+
+/// Clients may stop sending a request property, but may depend on a
+/// response property being there.
+fn properties(event: &Event, _: &RuleContext) -> Option<Severity> {
+    match event.kind {
+        PropertyRemoved { .. } => Some(now_optional(event)),
+        PropertyAdded { required: true, .. } => Some(now_required(event)),
+        PropertyAdded {
+            required: false, ..
+        } => Some(NonBreaking),
+        _ => None,
+    }
+}
+
+fn enums(event: &Event, cx: &RuleContext) -> Option<Severity> {
+    match (&event.kind, event.direction) {
+        (EnumValueRemoved { .. }, _) => Some(Breaking),
+        (EnumValueAdded { .. }, Some(Direction::Response)) if cx.options.tolerant_reader => {
+            Some(Warning)
+        }
+        (EnumValueAdded { .. }, Some(Direction::Response)) => Some(Breaking),
+        (EnumValueAdded { .. }, _) => Some(NonBreaking),
+        _ => None,
+    }
+}
+
+/// Each requirement is an alternative, so only removing one while
+/// credentials stay required, or adding the first to an operation that
+/// needed none, locks clients out.
+fn security(event: &Event, _: &RuleContext) -> Option<Severity> {
+    match event.kind {
+        SecurityRequirementRemoved {
+            still_open: true, ..
+        } => Some(NonBreaking),
+        SecurityRequirementRemoved { .. } | ScopeAdded { .. } => Some(Breaking),
+        SecurityRequirementAdded {
+            newly_required: true,
+            ..
+        } => Some(Breaking),
+        SecurityRequirementAdded { .. } | ScopeRemoved { .. } => Some(NonBreaking),
+        _ => None,
+    }
+}
+
+fn deprecation(event: &Event, _: &RuleContext) -> Option<Severity> {
+    match event.kind {
+        Deprecated { .. } | Undeprecated { .. } => Some(NonBreaking),
+        _ => None,
+    }
+}
+
+fn stability(event: &Event, _: &RuleContext) -> Option<Severity> {
+    let StabilityChanged { from, to } = &event.kind else {
+        return None;
+    };
+    Some(match (from.as_str(), to.as_str()) {
+        (_, STABLE) => NonBreaking,
+        (STABLE, _) => Breaking,
+        _ => Unstable,
+    })
+}
+
+fn documentation(event: &Event, _: &RuleContext) -> Option<Severity> {
+    match event.kind {
+        DocumentationAdded { .. }
+        | DocumentationRemoved { .. }
+        | DocumentationChanged { .. }
+        | TagAdded { .. }
+        | TagRemoved { .. } => Some(Info),
+        _ => None,
+    }
+}
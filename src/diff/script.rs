@@ -0,0 +1,236 @@
+use openapiv3::{OpenAPI, PathItem};
+use rhai::module_resolvers::DummyModuleResolver;
+use rhai::{AST, Dynamic, Engine, Map, Scope};
+use std::fmt;
+
+use super::{Event, Rule, RuleContext, operations, webhooks};
+use crate::change::{Change, Location, Severity};
+
+/// Budget of Rhai operations for one run of a script, so a runaway loop
+/// fails the script instead of hanging the diff.
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+/// A rule written in [Rhai](https://rhai.rs), run once per event: its
+/// verdict is computed once and used for both the severity and findings.
+///
+/// The script sees three variables:
+///
+/// - `event`: a map with the event's `kind` (such as `"ParameterAdded"`)
+///   and its fields (`name`, `in`, `required`, `from`, `to`, ...), plus
+///   `location`, `message`, `direction` (`"request"`, `"response"` or
+///   `()`), and the `rule` and `severity` the rules registered after the
+///   script would give it.
+/// - `old` and `new`: the operation, path item or schema component the
+///   event is in, as found in each spec, or `()`.
+///
+/// It returns `()` to leave the event alone, a severity such as
+/// `"breaking"` to override it, or a map with an optional `severity` and
+/// `findings`, an array of `#{ severity, message }` maps reported at the
+/// event's location.
+///
+/// Scripts run sandboxed: they cannot import modules, `eval` code or touch
+/// the filesystem, and are stopped after a fixed number of operations.
+pub struct ScriptRule {
+    name: String,
+    engine: Engine,
+    ast: AST,
+}
+
+/// A script that failed to compile.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct ScriptError {
+    /// Name of the rule.
+    pub rule: String,
+    /// What the Rhai compiler reported.
+    pub message: String,
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "rule '{}': {}", self.rule, self.message)
+    }
+}
+
+impl std::error::Error for ScriptError {}
+
+/// What a script returned for an event.
+#[derive(Clone, Default)]
+struct Verdict {
+    severity: Option<Severity>,
+    findings: Vec<(Severity, String)>,
+}
+
+impl ScriptRule {
+    /// Compile `source` into a rule called `name`.
+    pub fn new(name: impl Into<String>, source: &str) -> Result<Self, ScriptError> {
+        let name = name.into();
+        let engine = sandbox();
+        match engine.compile(source) {
+            Ok(ast) => Ok(ScriptRule { name, engine, ast }),
+            Err(e) => Err(ScriptError {
+                rule: name,
+                message: e.to_string(),
+            }),
+        }
+    }
+
+    fn run(&self, event: &Event, cx: &RuleContext) -> Result<Verdict, String> {
+        let mut event_map = to_map(&event.kind)?;
+        event_map.insert("location".into(), event.location.to_string().into());
+        event_map.insert("message".into(), event.kind.to_string().into());
+        event_map.insert("direction".into(), optional(event.direction));
+        let fallback = cx.fallback(event);
+        event_map.insert("rule".into(), optional(fallback.map(|(rule, _)| rule)));
+        event_map.insert("severity".into(), optional(fallback.map(|(_, s)| s)));
+
+        let mut scope = Scope::new();
+        scope.push("event", event_map);
+        scope.push("old", node(cx.old, &event.location)?);
+        scope.push("new", node(cx.new, &event.location)?);
+
+        let value: Dynamic = self
+            .engine
+            .eval_ast_with_scope(&mut scope, &self.ast)
+            .map_err(|e| e.to_string())?;
+        verdict(value)
+    }
+}
+
+impl Rule for ScriptRule {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn classify(&self, event: &Event, cx: &RuleContext) -> Option<Severity> {
+        cx.memo(|| self.run(event, cx)).ok()?.severity
+    }
+
+    /// The script's findings; a script that fails is reported as a warning
+    /// on the event's scope rather than silently ignored.
+    fn findings(&self, event: &Event, cx: &RuleContext) -> Vec<Change> {
+        let (location, findings) = match cx.memo(|| self.run(event, cx)) {
+            Ok(verdict) => (&event.location, verdict.findings),
+            Err(e) => (
+                event.location.scope(),
+                vec![(
+                    Severity::Warning,
+                    format!("rule '{}' failed: {e}", self.name),
+                )],
+            ),
+        };
+        findings
+            .into_iter()
+            .map(|(severity, message)| Change::new(severity, location.clone(), message))
+            .collect()
+    }
+}
+
+/// An engine without module loading, `eval` or output, and with limits on
+/// how much work a script may do.
+fn sandbox() -> Engine {
+    let mut engine = Engine::new();
+    engine.set_module_resolver(DummyModuleResolver::new());
+    engine.disable_symbol("eval");
+    engine.on_print(|_| {});
+    engine.on_debug(|_, _, _| {});
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine.set_max_call_levels(32);
+    engine.set_max_expr_depths(64, 32);
+    engine.set_max_string_size(1 << 20);
+    engine.set_max_array_size(10_000);
+    engine.set_max_map_size(10_000);
+    engine
+}
+
+fn to_map<T: serde::Serialize>(value: &T) -> Result<Map, String> {
+    rhai::serde::to_dynamic(value)
+        .map_err(|e| e.to_string())?
+        .try_cast::<Map>()
+        .ok_or_else(|| "expected a map".into())
+}
+
+fn optional<T: ToString>(value: Option<T>) -> Dynamic {
+    value.map_or(Dynamic::UNIT, |v| v.to_string().into())
+}
+
+fn verdict(value: Dynamic) -> Result<Verdict, String> {
+    if value.is_unit() {
+        return Ok(Verdict::default());
+    }
+    if value.is_string() {
+        return Ok(Verdict {
+            severity: Some(parse_severity(value)?),
+            findings: Vec::new(),
+        });
+    }
+    let Some(mut map) = value.try_cast::<Map>() else {
+        return Err("expected (), a severity or a map".into());
+    };
+    let severity = map.remove("severity").map(parse_severity).transpose()?;
+    let findings = match map.remove("findings") {
+        None => Vec::new(),
+        Some(findings) => findings
+            .into_typed_array::<Map>()
+            .map_err(|_| "findings must be an array of maps".to_string())?
+            .into_iter()
+            .map(|mut finding| {
+                let severity = parse_severity(finding.remove("severity").unwrap_or_default())?;
+                let message = finding
+                    .remove("message")
+                    .and_then(|m| m.into_string().ok())
+                    .ok_or("finding without a message")?;
+                Ok((severity, message))
+            })
+            .collect::<Result<_, String>>()?,
+    };
+    Ok(Verdict { severity, findings })
+}
+
+fn parse_severity(value: Dynamic) -> Result<Severity, String> {
+    value
+        .into_string()
+        .map_err(|t| format!("expected a severity, got {t}"))?
+        .parse()
+}
+
+/// The operation, path item or schema component enclosing `loc` in `spec`,
+/// or `()` if the spec doesn't have it.
+fn node(spec: &OpenAPI, loc: &Location) -> Result<Dynamic, String> {
+    let value = match loc.scope() {
+        Location::Path(path) | Location::Operation { path, .. } => spec
+            .paths
+            .paths
+            .get(path)
+            .and_then(|r| r.as_item())
+            .and_then(|item| item_node(item, loc.scope())),
+        Location::Webhook(inner) => match inner.as_ref() {
+            Location::Path(name) | Location::Operation { path: name, .. } => webhooks(spec)
+                .get(name)
+                .and_then(|item| item_node(item, inner)),
+            _ => None,
+        },
+        Location::Component(name) => spec
+            .components
+            .as_ref()
+            .and_then(|c| c.schemas.get(name))
+            .and_then(|schema| serde_json::to_value(schema).ok()),
+        _ => None,
+    };
+    match value {
+        Some(value) => rhai::serde::to_dynamic(value).map_err(|e| e.to_string()),
+        None => Ok(Dynamic::UNIT),
+    }
+}
+
+fn item_node(item: &PathItem, loc: &Location) -> Option<serde_json::Value> {
+    match loc {
+        Location::Path(_) => serde_json::to_value(item).ok(),
+        Location::Operation { method, .. } => operations(item)
+            .into_iter()
+            .find(|(m, _)| m == method)
+            .and_then(|(_, op)| op.as_ref())
+            .and_then(|op| serde_json::to_value(op).ok()),
+        _ => None,
+    }
+}
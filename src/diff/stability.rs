@@ -0,0 +1,188 @@
+use indexmap::IndexMap;
+use openapiv3::{OpenAPI, Operation, PathItem, ReferenceOr};
+
+use super::{Ctx, Event, EventKind, affected_usages, operations, rules, webhooks};
+use crate::change::{Change, Location, Severity};
+
+const EXTENSION: &str = "x-stability";
+pub(super) const STABLE: &str = "stable";
+
+fn label(extensions: &IndexMap<String, serde_json::Value>) -> Option<String> {
+    extensions
+        .get(EXTENSION)
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_ascii_lowercase())
+}
+
+/// Stability of an operation: its own `x-stability`, then its path item's,
+/// then the first of its tags that declares one.
+fn operation_label(spec: &OpenAPI, item: &PathItem, op: &Operation) -> String {
+    label(&op.extensions)
+        .or_else(|| label(&item.extensions))
+        .or_else(|| {
+            op.tags.iter().find_map(|name| {
+                spec.tags
+                    .iter()
+                    .find(|t| t.name == *name)
+                    .and_then(|t| label(&t.extensions))
+            })
+        })
+        .unwrap_or_else(|| STABLE.into())
+}
+
+/// Stability of a path item: its own `x-stability`, or else that of its
+/// operations when none of them is stable.
+fn path_label(spec: &OpenAPI, item: &PathItem) -> String {
+    if let Some(label) = label(&item.extensions) {
+        return label;
+    }
+    let labels: Vec<String> = operations(item)
+        .into_iter()
+        .filter_map(|(_, op)| op.as_ref())
+        .map(|op| operation_label(spec, item, op))
+        .collect();
+    match labels.iter().find(|l| *l != STABLE) {
+        Some(label) if labels.iter().all(|l| l != STABLE) => label.clone(),
+        _ => STABLE.into(),
+    }
+}
+
+fn item_label(spec: &OpenAPI, item: &PathItem, loc: &Location) -> Option<String> {
+    match loc {
+        Location::Path(_) => Some(path_label(spec, item)),
+        Location::Operation { method, .. } => operations(item)
+            .into_iter()
+            .find(|(m, _)| m == method)
+            .and_then(|(_, op)| op.as_ref())
+            .map(|op| operation_label(spec, item, op)),
+        _ => None,
+    }
+}
+
+/// Stability of whatever `loc` points at in `spec`, or `None` if the spec
+/// does not contain it.
+fn location_label(spec: &OpenAPI, loc: &Location) -> Option<String> {
+    match loc {
+        Location::Document | Location::Component(_) => Some(STABLE.into()),
+        Location::Path(path) | Location::Operation { path, .. } => spec
+            .paths
+            .paths
+            .get(path)
+            .and_then(|r| r.as_item())
+            .and_then(|item| item_label(spec, item, loc)),
+        Location::Callback { parent, .. } => location_label(spec, parent),
+        Location::Webhook(inner) => {
+            let (Location::Path(name) | Location::Operation { path: name, .. }) = inner.as_ref()
+            else {
+                return None;
+            };
+            webhooks(spec)
+                .get(name)
+                .and_then(|item| item_label(spec, item, inner))
+        }
+        detail => location_label(spec, detail.scope()),
+    }
+}
+
+/// A location is unstable if the old spec marks it so, or if it is new and
+/// the new spec marks it so.
+fn is_unstable(cx: &Ctx, loc: &Location) -> bool {
+    match loc.scope() {
+        Location::Component(name) => {
+            let usages = cx.usages.borrow();
+            let affected = affected_usages(&usages, name, None);
+            !affected.is_empty() && affected.iter().all(|u| is_unstable(cx, &u.location))
+        }
+        scope => location_label(cx.old, scope)
+            .or_else(|| location_label(cx.new, scope))
+            .is_some_and(|label| label != STABLE),
+    }
+}
+
+/// Downgrade changes under non-stable surfaces to `Severity::Unstable`, then
+/// report operations whose stability level changed.
+pub(super) fn apply(cx: &Ctx, changes: Vec<Change>) -> Vec<Change> {
+    let downgraded = changes.into_iter().map(|mut change| {
+        if is_unstable(cx, &change.location) {
+            change.severity = Severity::Unstable;
+        }
+        change
+    });
+
+    downgraded
+        .chain(rules::classify(cx, &diff_levels(cx)))
+        .collect()
+}
+
+fn diff_levels(cx: &Ctx) -> Vec<Event> {
+    let paths = diff_item_levels(
+        cx,
+        &cx.old.paths.paths,
+        &cx.new.paths.paths,
+        ReferenceOr::as_item,
+        &|loc| loc,
+    );
+    let webhooks = diff_item_levels(
+        cx,
+        &webhooks(cx.old),
+        &webhooks(cx.new),
+        |item| Some(item),
+        &|loc| Location::Webhook(Box::new(loc)),
+    );
+    paths.into_iter().chain(webhooks).collect()
+}
+
+/// Stability changes of the operations two maps of path items share,
+/// including those in their callbacks, located like `diff_path_items` does.
+fn diff_item_levels<T>(
+    cx: &Ctx,
+    old: &IndexMap<String, T>,
+    new: &IndexMap<String, T>,
+    as_item: fn(&T) -> Option<&PathItem>,
+    locate: &dyn Fn(Location) -> Location,
+) -> Vec<Event> {
+    old.iter()
+        .filter_map(|(path, old_ref)| {
+            let new_ref = new.get(path)?;
+            Some((path, as_item(old_ref)?, as_item(new_ref)?))
+        })
+        .flat_map(|(path, old_item, new_item)| {
+            operations(old_item)
+                .into_iter()
+                .zip(operations(new_item))
+                .filter_map(|((method, old_op), (_, new_op))| {
+                    Some((method, old_op.as_ref()?, new_op.as_ref()?))
+                })
+                .flat_map(move |(method, old_op, new_op)| {
+                    let location = locate(Location::Operation {
+                        path: path.clone(),
+                        method: method.to_string(),
+                    });
+                    let old_label = operation_label(cx.old, old_item, old_op);
+                    let new_label = operation_label(cx.new, new_item, new_op);
+                    let changed = (old_label != new_label).then(|| {
+                        let kind = EventKind::StabilityChanged {
+                            from: old_label,
+                            to: new_label,
+                        };
+                        Event::new(&location, kind)
+                    });
+                    let callbacks = old_op.callbacks.iter().flat_map(|(name, old_cb)| {
+                        let locate = |inner| Location::Callback {
+                            parent: Box::new(location.clone()),
+                            name: name.clone(),
+                            inner: Box::new(inner),
+                        };
+                        new_op
+                            .callbacks
+                            .get(name)
+                            .map(|new_cb| {
+                                diff_item_levels(cx, old_cb, new_cb, |item| Some(item), &locate)
+                            })
+                            .unwrap_or_default()
+                    });
+                    changed.into_iter().chain(callbacks).collect::<Vec<_>>()
+                })
+        })
+        .collect()
+}
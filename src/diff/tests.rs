@@ -1,5 +1,7 @@
 use super::*;
 use crate::change::{Location, Severity};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 fn parse_spec(yaml: &str) -> OpenAPI {
     serde_yml::from_str(yaml).expect("test spec should parse")
@@ -168,7 +170,11 @@ paths:
     let changes = diff_specs(&old, &new);
     assert_eq!(changes.len(), 1);
     assert_eq!(changes[0].severity, Severity::Breaking);
-    assert!(changes[0].message.contains("'filter'"));
+    assert!(
+        changes[0]
+            .to_string()
+            .contains("query parameter 'filter': added")
+    );
 }
 
 #[test]
@@ -311,7 +317,7 @@ paths:
     let changes = diff_specs(&old, &new);
     assert_eq!(changes.len(), 1);
     assert_eq!(changes[0].severity, Severity::Breaking);
-    assert!(changes[0].message.contains("response '404' removed"));
+    assert!(changes[0].to_string().contains("response '404': removed"));
 }
 
 #[test]
@@ -355,7 +361,11 @@ paths:
     let changes = diff_specs(&old, &new);
     assert_eq!(changes.len(), 1);
     assert_eq!(changes[0].severity, Severity::Breaking);
-    assert!(changes[0].message.contains("'name' removed"));
+    assert!(
+        changes[0]
+            .to_string()
+            .contains("application/json.name: removed")
+    );
 }
 
 #[test]
@@ -408,7 +418,11 @@ paths:
     let diff = diff_specs(&old, &new);
     let breaking = diff.breaking();
     assert_eq!(breaking.len(), 1);
-    assert!(breaking[0].message.contains("'email' added"));
+    assert!(
+        breaking[0]
+            .to_string()
+            .contains("application/json.email: added")
+    );
 }
 
 #[test]
@@ -582,7 +596,11 @@ components:
     let changes = diff_specs(&old, &new);
     assert_eq!(changes.len(), 1);
     assert_eq!(changes[0].severity, Severity::Breaking);
-    assert!(changes[0].message.contains("'name' removed"));
+    assert!(
+        changes[0]
+            .to_string()
+            .contains("application/json.name: removed")
+    );
 }
 
 #[test]
@@ -630,5 +648,3061 @@ paths:
     let changes = diff_specs(&old, &new);
     assert_eq!(changes.len(), 1);
     assert_eq!(changes[0].severity, Severity::NonBreaking);
-    assert!(changes[0].message.contains("'bio' removed"));
+    assert!(
+        changes[0]
+            .to_string()
+            .contains("application/json.bio: removed")
+    );
+}
+
+#[test]
+fn server_removed_is_breaking() {
+    let old = parse_spec(&minimal_spec(
+        r#"
+servers:
+  - url: https://api.example.com
+  - url: https://eu.api.example.com
+paths: {}
+"#,
+    ));
+    let new = parse_spec(&minimal_spec(
+        r#"
+servers:
+  - url: https://api.example.com
+paths: {}
+"#,
+    ));
+    let changes = diff_specs(&old, &new);
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].severity, Severity::Breaking);
+    assert_eq!(
+        changes[0].location,
+        Location::Document.server("https://eu.api.example.com")
+    );
+    assert_eq!(changes[0].message, "removed");
+}
+
+#[test]
+fn server_variable_enum_and_default_changes() {
+    let old = parse_spec(&minimal_spec(
+        r#"
+servers:
+  - url: https://{region}.example.com
+    variables:
+      region:
+        default: us
+        enum: [us, eu]
+paths: {}
+"#,
+    ));
+    let new = parse_spec(&minimal_spec(
+        r#"
+servers:
+  - url: https://{region}.example.com
+    variables:
+      region:
+        default: eu
+        enum: [eu, ap]
+paths: {}
+"#,
+    ));
+    let changes = diff_specs(&old, &new);
+    assert_eq!(changes.len(), 3);
+    assert!(
+        changes[0]
+            .message
+            .contains("default changed from 'us' to 'eu'")
+    );
+    assert!(changes[1].message.contains("enum value 'us' removed"));
+    assert_eq!(changes[1].severity, Severity::Breaking);
+    assert!(changes[2].message.contains("enum value 'ap' added"));
+    assert_eq!(changes[2].severity, Severity::NonBreaking);
+}
+
+#[test]
+fn base_path_change_is_breaking_for_every_operation() {
+    let paths = r#"
+paths:
+  /users:
+    get:
+      responses:
+        "200":
+          description: OK
+    post:
+      servers:
+        - url: https://upload.example.com/v1
+      responses:
+        "201":
+          description: Created
+"#;
+    let old = parse_spec(&minimal_spec(&format!(
+        "servers:\n  - url: https://example.com/api\n{paths}"
+    )));
+    let new = parse_spec(&minimal_spec(&format!(
+        "servers:\n  - url: https://example.com/api/v2/\n{paths}"
+    )));
+    let changes = diff_specs(&old, &new);
+    let base_changes: Vec<_> = changes
+        .breaking()
+        .into_iter()
+        .filter(|c| c.message.contains("base path"))
+        .collect();
+    assert_eq!(base_changes.len(), 1);
+    assert!(
+        base_changes[0]
+            .message
+            .contains("base path changed from '/api' to '/api/v2'")
+    );
+    assert!(
+        matches!(&base_changes[0].location, Location::Operation { method, .. } if method == "GET")
+    );
+}
+
+#[test]
+fn callback_request_property_removed_is_breaking() {
+    let old = parse_spec(&minimal_spec(
+        r#"
+paths:
+  /subscribe:
+    post:
+      responses:
+        "201":
+          description: Subscribed
+      callbacks:
+        onEvent:
+          "{$request.body#/url}":
+            post:
+              requestBody:
+                required: true
+                content:
+                  application/json:
+                    schema:
+                      type: object
+                      properties:
+                        id:
+                          type: string
+                        kind:
+                          type: string
+              responses:
+                "200":
+                  description: OK
+"#,
+    ));
+    let new = parse_spec(&minimal_spec(
+        r#"
+paths:
+  /subscribe:
+    post:
+      responses:
+        "201":
+          description: Subscribed
+      callbacks:
+        onEvent:
+          "{$request.body#/url}":
+            post:
+              requestBody:
+                required: true
+                content:
+                  application/json:
+                    schema:
+                      type: object
+                      properties:
+                        id:
+                          type: string
+              responses:
+                "200":
+                  description: OK
+"#,
+    ));
+    let changes = diff_specs(&old, &new);
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].severity, Severity::Breaking);
+    assert!(
+        changes[0]
+            .to_string()
+            .ends_with("application/json.kind: removed")
+    );
+    assert!(
+        changes[0]
+            .to_string()
+            .starts_with("POST /subscribe -> callback onEvent POST {$request.body#/url} - ")
+    );
+}
+
+#[test]
+fn callback_request_required_property_added_is_non_breaking() {
+    let old = parse_spec(&minimal_spec(
+        r#"
+paths:
+  /subscribe:
+    post:
+      responses:
+        "201":
+          description: Subscribed
+      callbacks:
+        onEvent:
+          "{$request.body#/url}":
+            post:
+              requestBody:
+                required: true
+                content:
+                  application/json:
+                    schema:
+                      type: object
+                      properties:
+                        id:
+                          type: string
+              responses:
+                "200":
+                  description: OK
+"#,
+    ));
+    let new = parse_spec(&minimal_spec(
+        r#"
+paths:
+  /subscribe:
+    post:
+      responses:
+        "201":
+          description: Subscribed
+      callbacks:
+        onEvent:
+          "{$request.body#/url}":
+            post:
+              requestBody:
+                required: true
+                content:
+                  application/json:
+                    schema:
+                      type: object
+                      required: [kind]
+                      properties:
+                        id:
+                          type: string
+                        kind:
+                          type: string
+              responses:
+                "200":
+                  description: OK
+"#,
+    ));
+    let changes = diff_specs(&old, &new);
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].severity, Severity::NonBreaking);
+    assert!(
+        changes[0]
+            .to_string()
+            .ends_with("application/json.kind: added")
+    );
+}
+
+#[test]
+fn webhook_removed_is_breaking() {
+    let old = parse_spec(&minimal_spec(
+        r#"
+paths: {}
+x-webhooks:
+  newPet:
+    post:
+      responses:
+        "200":
+          description: OK
+"#,
+    ));
+    let new = parse_spec(&minimal_spec("paths: {}"));
+    let changes = diff_specs(&old, &new);
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].severity, Severity::Breaking);
+    assert_eq!(changes[0].to_string(), "webhook newPet - endpoint removed");
+}
+
+#[test]
+fn openapi_31_webhook_removed_is_breaking() {
+    let old = crate::loader::parse_str(
+        r#"
+openapi: "3.1.0"
+info:
+  title: Test
+  version: "1.0.0"
+paths: {}
+webhooks:
+  newPet:
+    post:
+      responses:
+        "200":
+          description: OK
+"#,
+    )
+    .unwrap();
+    let new = parse_spec(&minimal_spec("paths: {}"));
+    let changes = diff_specs(&old, &new);
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].severity, Severity::Breaking);
+    assert_eq!(changes[0].to_string(), "webhook newPet - endpoint removed");
+}
+
+#[test]
+fn media_type_parameters_and_case_are_ignored() {
+    let old = parse_spec(&minimal_spec(
+        r#"
+paths:
+  /pets:
+    post:
+      requestBody:
+        content:
+          application/json:
+            schema:
+              type: string
+      responses:
+        "200":
+          description: OK
+"#,
+    ));
+    let new = parse_spec(&minimal_spec(
+        r#"
+paths:
+  /pets:
+    post:
+      requestBody:
+        content:
+          Application/JSON; charset=utf-8:
+            schema:
+              type: string
+      responses:
+        "200":
+          description: OK
+"#,
+    ));
+    assert!(diff_specs(&old, &new).is_empty());
+}
+
+#[test]
+fn request_media_type_covered_by_wildcard_is_not_removed() {
+    let old = parse_spec(&minimal_spec(
+        r#"
+paths:
+  /pets:
+    post:
+      requestBody:
+        content:
+          application/json:
+            schema:
+              type: string
+      responses:
+        "200":
+          description: OK
+"#,
+    ));
+    let new = parse_spec(&minimal_spec(
+        r#"
+paths:
+  /pets:
+    post:
+      requestBody:
+        content:
+          application/*:
+            schema:
+              type: string
+      responses:
+        "200":
+          description: OK
+"#,
+    ));
+    let changes = diff_specs(&old, &new);
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].severity, Severity::NonBreaking);
+    assert!(
+        changes[0]
+            .to_string()
+            .ends_with("application/json: covered by 'application/*'")
+    );
+}
+
+#[test]
+fn covered_media_type_examples_are_compared() {
+    let old = parse_spec(&minimal_spec(
+        r#"
+paths:
+  /pets:
+    post:
+      requestBody:
+        content:
+          application/json:
+            example: a
+      responses:
+        "200":
+          description: OK
+"#,
+    ));
+    let new = parse_spec(&minimal_spec(
+        r#"
+paths:
+  /pets:
+    post:
+      requestBody:
+        content:
+          application/*:
+            example: b
+      responses:
+        "200":
+          description: OK
+"#,
+    ));
+    let changes = diff_specs(&old, &new);
+    assert_eq!(changes.len(), 2);
+    assert!(
+        changes[1]
+            .to_string()
+            .ends_with("application/json: example changed")
+    );
+    // Both sides of the pair can be found from the location.
+    let Location::MediaType { new_media, .. } = &changes[1].location else {
+        panic!("unexpected location {:?}", changes[1].location);
+    };
+    assert_eq!(new_media.as_deref(), Some("application/*"));
+}
+
+#[test]
+fn request_media_type_removed_is_breaking() {
+    let old = parse_spec(&minimal_spec(
+        r#"
+paths:
+  /pets:
+    post:
+      requestBody:
+        content:
+          application/json:
+            schema:
+              type: string
+          application/xml:
+            schema:
+              type: string
+      responses:
+        "200":
+          description: OK
+"#,
+    ));
+    let new = parse_spec(&minimal_spec(
+        r#"
+paths:
+  /pets:
+    post:
+      requestBody:
+        content:
+          application/json:
+            schema:
+              type: string
+      responses:
+        "200":
+          description: OK
+"#,
+    ));
+    let changes = diff_specs(&old, &new);
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].severity, Severity::Breaking);
+}
+
+#[test]
+fn response_alternative_media_type_removed_is_non_breaking() {
+    let old = parse_spec(&minimal_spec(
+        r#"
+paths:
+  /pets:
+    post:
+      responses:
+        "200":
+          description: OK
+          content:
+            application/json:
+              schema:
+                type: string
+            application/xml:
+              schema:
+                type: string
+"#,
+    ));
+    let new = parse_spec(&minimal_spec(
+        r#"
+paths:
+  /pets:
+    post:
+      responses:
+        "200":
+          description: OK
+          content:
+            application/json:
+              schema:
+                type: string
+"#,
+    ));
+    let changes = diff_specs(&old, &new);
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].severity, Severity::NonBreaking);
+    assert!(changes[0].to_string().ends_with("application/xml: removed"));
+}
+
+#[test]
+fn media_type_replaced_by_structured_suffix_is_breaking() {
+    let old = parse_spec(&minimal_spec(
+        r#"
+paths:
+  /pets:
+    post:
+      responses:
+        "200":
+          description: OK
+          content:
+            application/json:
+              schema:
+                type: string
+"#,
+    ));
+    let new = parse_spec(&minimal_spec(
+        r#"
+paths:
+  /pets:
+    post:
+      responses:
+        "200":
+          description: OK
+          content:
+            application/problem+json:
+              schema:
+                type: string
+"#,
+    ));
+    let changes = diff_specs(&old, &new);
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].severity, Severity::Breaking);
+    assert!(
+        changes[0]
+            .to_string()
+            .ends_with("application/json: replaced by 'application/problem+json'")
+    );
+}
+
+#[test]
+fn response_folded_into_range_is_not_removed() {
+    let old = parse_spec(&minimal_spec(
+        r#"
+paths:
+  /pets:
+    get:
+      responses:
+        "200":
+          description: OK
+        "404":
+          description: Missing
+"#,
+    ));
+    let new = parse_spec(&minimal_spec(
+        r#"
+paths:
+  /pets:
+    get:
+      responses:
+        "200":
+          description: OK
+        "4XX":
+          description: Client error
+"#,
+    ));
+    let changes = diff_specs(&old, &new);
+    assert!(!changes.has_breaking());
+    assert!(
+        changes[0]
+            .to_string()
+            .contains("response '404': now covered by '4XX'")
+    );
+    assert!(changes[2].to_string().ends_with("response '4XX': added"));
+}
+
+#[test]
+fn response_folded_into_default_is_diffed_against_default() {
+    let old = parse_spec(&minimal_spec(
+        r#"
+paths:
+  /pets:
+    get:
+      responses:
+        "500":
+          description: Error
+          content:
+            application/json:
+              schema:
+                type: object
+                properties:
+                  code:
+                    type: integer
+"#,
+    ));
+    let new = parse_spec(&minimal_spec(
+        r#"
+paths:
+  /pets:
+    get:
+      responses:
+        default:
+          description: Error
+          content:
+            application/json:
+              schema:
+                type: object
+                properties: {}
+"#,
+    ));
+    let changes = diff_specs(&old, &new);
+    assert_eq!(changes.len(), 3);
+    assert!(changes[0].message.contains("now covered by 'default'"));
+    assert_eq!(changes[1].severity, Severity::Breaking);
+    assert!(
+        changes[1]
+            .to_string()
+            .contains("response '500' application/json.code: removed")
+    );
+    assert!(
+        changes[2]
+            .to_string()
+            .ends_with("response 'default': added")
+    );
+}
+
+#[test]
+fn primary_success_code_change_is_breaking() {
+    let old = parse_spec(&minimal_spec(
+        r#"
+paths:
+  /pets:
+    get:
+      responses:
+        "200":
+          description: OK
+"#,
+    ));
+    let new = parse_spec(&minimal_spec(
+        r#"
+paths:
+  /pets:
+    get:
+      responses:
+        "201":
+          description: Created
+"#,
+    ));
+    let changes = diff_specs(&old, &new);
+    assert_eq!(changes.len(), 1);
+    assert_eq!(
+        changes[0].message,
+        "primary success response changed from '200' to '201'"
+    );
+    assert_eq!(changes[0].severity, Severity::Breaking);
+
+    // Clients already handle a success code that was documented before.
+    let both = parse_spec(&minimal_spec(
+        r#"
+paths:
+  /pets:
+    get:
+      responses:
+        "200":
+          description: OK
+        "201":
+          description: Created
+"#,
+    ));
+    let changes = diff_specs(&both, &new);
+    assert_eq!(changes.len(), 1);
+    assert!(changes[0].to_string().ends_with("response '200': removed"));
+    assert_eq!(changes[0].severity, Severity::Breaking);
+}
+
+#[test]
+fn recursive_schema_is_compared_once() {
+    let old = parse_spec(&minimal_spec(
+        r##"
+paths:
+  /tree:
+    get:
+      responses:
+        "200":
+          description: OK
+          content:
+            application/json:
+              schema:
+                $ref: "#/components/schemas/Node"
+components:
+  schemas:
+    Node:
+      type: object
+      properties:
+        children:
+          type: array
+          items:
+            $ref: "#/components/schemas/Node"
+        label:
+          type: string
+"##,
+    ));
+    let new = parse_spec(&minimal_spec(
+        r##"
+paths:
+  /tree:
+    get:
+      responses:
+        "200":
+          description: OK
+          content:
+            application/json:
+              schema:
+                $ref: "#/components/schemas/Node"
+components:
+  schemas:
+    Node:
+      type: object
+      properties:
+        children:
+          type: array
+          items:
+            $ref: "#/components/schemas/Node"
+"##,
+    ));
+    let changes = diff_specs(&old, &new);
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].severity, Severity::Breaking);
+    assert!(
+        changes[0]
+            .to_string()
+            .contains("application/json.label: removed")
+    );
+}
+
+#[test]
+fn deeply_nested_changes_are_detected() {
+    let nested = |leaf: &str| {
+        let mut schema = format!("type: object\nproperties:\n  {leaf}:\n    type: string");
+        for _ in 0..12 {
+            let indented = schema.replace('\n', "\n    ");
+            schema = format!("type: object\nproperties:\n  next:\n    {indented}");
+        }
+        let indented = schema.replace('\n', "\n                ");
+        parse_spec(&minimal_spec(&format!(
+            r#"
+paths:
+  /deep:
+    get:
+      responses:
+        "200":
+          description: OK
+          content:
+            application/json:
+              schema:
+                {indented}
+"#
+        )))
+    };
+    let changes = diff_specs(&nested("id"), &nested("uuid"));
+    assert_eq!(changes.len(), 2);
+    assert!(changes[0].to_string().contains(".next.next.next."));
+    assert!(changes[0].to_string().ends_with(".next.id: removed"));
+}
+
+#[test]
+fn shared_component_inlined_at_every_use_by_default() {
+    let old = parse_spec(&minimal_spec(
+        r##"
+paths:
+  /pets:
+    get:
+      responses:
+        "200":
+          description: OK
+          content:
+            application/json:
+              schema:
+                type: array
+                items:
+                  $ref: "#/components/schemas/Pet"
+    post:
+      requestBody:
+        content:
+          application/json:
+            schema:
+              $ref: "#/components/schemas/Pet"
+      responses:
+        "201":
+          description: Created
+  /pets/{petId}:
+    get:
+      responses:
+        "200":
+          description: OK
+          content:
+            application/json:
+              schema:
+                $ref: "#/components/schemas/Pet"
+components:
+  schemas:
+    Owner:
+      type: object
+      properties:
+        name:
+          type: string
+    Pet:
+      type: object
+      properties:
+        owner:
+          $ref: "#/components/schemas/Owner"
+        tag:
+          type: string
+"##,
+    ));
+    let new = parse_spec(&minimal_spec(
+        r##"
+paths:
+  /pets:
+    get:
+      responses:
+        "200":
+          description: OK
+          content:
+            application/json:
+              schema:
+                type: array
+                items:
+                  $ref: "#/components/schemas/Pet"
+    post:
+      requestBody:
+        content:
+          application/json:
+            schema:
+              $ref: "#/components/schemas/Pet"
+      responses:
+        "201":
+          description: Created
+  /pets/{petId}:
+    get:
+      responses:
+        "200":
+          description: OK
+          content:
+            application/json:
+              schema:
+                $ref: "#/components/schemas/Pet"
+components:
+  schemas:
+    Owner:
+      type: object
+      properties:
+        name:
+          type: string
+    Pet:
+      type: object
+      properties:
+        owner:
+          $ref: "#/components/schemas/Owner"
+"##,
+    ));
+    let changes = diff_specs(&old, &new);
+    assert_eq!(changes.len(), 3);
+}
+
+#[test]
+fn grouped_component_change_reported_once_with_usages() {
+    let old = parse_spec(&minimal_spec(
+        r##"
+paths:
+  /pets:
+    get:
+      responses:
+        "200":
+          description: OK
+          content:
+            application/json:
+              schema:
+                type: array
+                items:
+                  $ref: "#/components/schemas/Pet"
+    post:
+      requestBody:
+        content:
+          application/json:
+            schema:
+              $ref: "#/components/schemas/Pet"
+      responses:
+        "201":
+          description: Created
+  /pets/{petId}:
+    get:
+      responses:
+        "200":
+          description: OK
+          content:
+            application/json:
+              schema:
+                $ref: "#/components/schemas/Pet"
+components:
+  schemas:
+    Owner:
+      type: object
+      properties:
+        name:
+          type: string
+    Pet:
+      type: object
+      properties:
+        owner:
+          $ref: "#/components/schemas/Owner"
+        tag:
+          type: string
+"##,
+    ));
+    let new = parse_spec(&minimal_spec(
+        r##"
+paths:
+  /pets:
+    get:
+      responses:
+        "200":
+          description: OK
+          content:
+            application/json:
+              schema:
+                type: array
+                items:
+                  $ref: "#/components/schemas/Pet"
+    post:
+      requestBody:
+        content:
+          application/json:
+            schema:
+              $ref: "#/components/schemas/Pet"
+      responses:
+        "201":
+          description: Created
+  /pets/{petId}:
+    get:
+      responses:
+        "200":
+          description: OK
+          content:
+            application/json:
+              schema:
+                $ref: "#/components/schemas/Pet"
+components:
+  schemas:
+    Owner:
+      type: object
+      properties:
+        name:
+          type: string
+    Pet:
+      type: object
+      properties:
+        owner:
+          $ref: "#/components/schemas/Owner"
+"##,
+    ));
+    let options = DiffOptions::new().group_components(true);
+    let changes = diff_specs_with(&old, &new, &options);
+    assert_eq!(changes.len(), 1);
+    assert_eq!(
+        changes[0].location,
+        Location::Component("Pet".into()).property("tag")
+    );
+    assert_eq!(changes[0].severity, Severity::Breaking);
+    assert_eq!(
+        changes[0].to_string(),
+        "component Pet - Pet.tag: removed (breaking because the schema is also used \
+         in a response for GET /pets, GET /pets/{petId})"
+    );
+
+    let affected: Vec<String> = changes
+        .affected_by("Pet")
+        .iter()
+        .map(|u| format!("{} ({})", u.location, u.direction))
+        .collect();
+    assert_eq!(
+        affected,
+        [
+            "GET /pets (response)",
+            "POST /pets (request)",
+            "GET /pets/{petId} (response)"
+        ]
+    );
+}
+
+#[test]
+fn nested_component_usages_resolve_to_operations() {
+    let old = parse_spec(&minimal_spec(
+        r##"
+paths:
+  /pets:
+    get:
+      responses:
+        "200":
+          description: OK
+          content:
+            application/json:
+              schema:
+                type: array
+                items:
+                  $ref: "#/components/schemas/Pet"
+    post:
+      requestBody:
+        content:
+          application/json:
+            schema:
+              $ref: "#/components/schemas/Pet"
+      responses:
+        "201":
+          description: Created
+  /pets/{petId}:
+    get:
+      responses:
+        "200":
+          description: OK
+          content:
+            application/json:
+              schema:
+                $ref: "#/components/schemas/Pet"
+components:
+  schemas:
+    Owner:
+      type: object
+      properties:
+        name:
+          type: string
+    Pet:
+      type: object
+      properties:
+        owner:
+          $ref: "#/components/schemas/Owner"
+"##,
+    ));
+    let new = parse_spec(&minimal_spec(
+        r##"
+paths:
+  /pets:
+    get:
+      responses:
+        "200":
+          description: OK
+          content:
+            application/json:
+              schema:
+                type: array
+                items:
+                  $ref: "#/components/schemas/Pet"
+    post:
+      requestBody:
+        content:
+          application/json:
+            schema:
+              $ref: "#/components/schemas/Pet"
+      responses:
+        "201":
+          description: Created
+  /pets/{petId}:
+    get:
+      responses:
+        "200":
+          description: OK
+          content:
+            application/json:
+              schema:
+                $ref: "#/components/schemas/Pet"
+components:
+  schemas:
+    Owner:
+      type: object
+      properties:
+        name:
+          type: integer
+    Pet:
+      type: object
+      properties:
+        owner:
+          $ref: "#/components/schemas/Owner"
+"##,
+    ));
+    let options = DiffOptions::new().group_components(true);
+    let changes = diff_specs_with(&old, &new, &options);
+    assert_eq!(changes.len(), 1);
+    assert_eq!(
+        changes[0].location,
+        Location::Component("Owner".into()).property("name")
+    );
+    assert_eq!(
+        changes[0].to_string(),
+        "component Owner - Owner.name: type changed from string to integer"
+    );
+    assert_eq!(changes.affected_by("Owner").len(), 3);
+}
+
+#[test]
+fn changes_under_beta_operations_are_unstable() {
+    let old = parse_spec(&minimal_spec(
+        r#"
+tags:
+  - name: labs
+    x-stability: internal
+paths:
+  /search:
+    get:
+      x-stability: beta
+      responses:
+        "200":
+          description: OK
+  /labs:
+    get:
+      tags: [labs]
+      responses:
+        "200":
+          description: OK
+"#,
+    ));
+    let new = parse_spec(&minimal_spec(
+        r#"
+tags:
+  - name: labs
+    x-stability: internal
+paths:
+  /search:
+    get:
+      x-stability: beta
+      parameters:
+        - name: q
+          in: query
+          required: true
+          schema:
+            type: string
+      responses:
+        "200":
+          description: OK
+  /labs:
+    get:
+      tags: [labs]
+      parameters:
+        - name: q
+          in: query
+          required: true
+          schema:
+            type: string
+      responses:
+        "200":
+          description: OK
+"#,
+    ));
+    let changes = diff_specs(&old, &new);
+    assert_eq!(changes.len(), 2);
+    assert!(!changes.has_breaking());
+    assert_eq!(changes.unstable().len(), 2);
+}
+
+#[test]
+fn promotion_to_stable_is_reported() {
+    let old = parse_spec(&minimal_spec(
+        r#"
+tags:
+  - name: labs
+    x-stability: internal
+paths:
+  /search:
+    get:
+      x-stability: beta
+      responses:
+        "200":
+          description: OK
+  /labs:
+    get:
+      tags: [labs]
+      responses:
+        "200":
+          description: OK
+"#,
+    ));
+    let new = parse_spec(&minimal_spec(
+        r#"
+tags:
+  - name: labs
+    x-stability: internal
+paths:
+  /search:
+    get:
+      x-stability: stable
+      responses:
+        "200":
+          description: OK
+  /labs:
+    get:
+      tags: [labs]
+      responses:
+        "200":
+          description: OK
+"#,
+    ));
+    let changes = diff_specs(&old, &new);
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].severity, Severity::NonBreaking);
+    assert_eq!(
+        changes[0].to_string(),
+        "GET /search - promoted from beta to stable"
+    );
+}
+
+#[test]
+fn demotion_from_stable_is_breaking() {
+    let old = parse_spec(&minimal_spec(
+        r#"
+tags:
+  - name: labs
+    x-stability: stable
+paths:
+  /search:
+    get:
+      x-stability: stable
+      responses:
+        "200":
+          description: OK
+  /labs:
+    get:
+      tags: [labs]
+      responses:
+        "200":
+          description: OK
+"#,
+    ));
+    let new = parse_spec(&minimal_spec(
+        r#"
+tags:
+  - name: labs
+    x-stability: beta
+paths:
+  /search:
+    get:
+      x-stability: stable
+      responses:
+        "200":
+          description: OK
+  /labs:
+    get:
+      tags: [labs]
+      responses:
+        "200":
+          description: OK
+"#,
+    ));
+    let changes = diff_specs(&old, &new);
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].severity, Severity::Breaking);
+    assert!(changes[0].message.contains("demoted from stable to beta"));
+}
+
+#[test]
+fn stability_of_callbacks_and_webhooks_is_compared() {
+    let old = parse_spec(&minimal_spec(
+        r#"
+paths:
+  /subscribe:
+    post:
+      responses:
+        "201":
+          description: Subscribed
+      callbacks:
+        onEvent:
+          "{$request.body#/url}":
+            post:
+              x-stability: beta
+              responses:
+                "200":
+                  description: OK
+x-webhooks:
+  newPet:
+    post:
+      x-stability: beta
+      responses:
+        "200":
+          description: OK
+"#,
+    ));
+    let new = parse_spec(&minimal_spec(
+        r#"
+paths:
+  /subscribe:
+    post:
+      responses:
+        "201":
+          description: Subscribed
+      callbacks:
+        onEvent:
+          "{$request.body#/url}":
+            post:
+              x-stability: stable
+              responses:
+                "200":
+                  description: OK
+x-webhooks:
+  newPet:
+    post:
+      x-stability: stable
+      responses:
+        "200":
+          description: OK
+"#,
+    ));
+    let changes = diff_specs(&old, &new);
+    let described: Vec<String> = changes.changes().iter().map(ToString::to_string).collect();
+    assert_eq!(
+        described,
+        [
+            "POST /subscribe -> callback onEvent POST {$request.body#/url} - promoted from beta to stable",
+            "webhook POST newPet - promoted from beta to stable",
+        ]
+    );
+}
+
+#[test]
+fn removing_paths_of_unstable_operations_is_unstable() {
+    let old = parse_spec(&minimal_spec(
+        r#"
+tags:
+  - name: labs
+    x-stability: internal
+paths:
+  /search:
+    get:
+      x-stability: beta
+      responses:
+        "200":
+          description: OK
+  /labs:
+    get:
+      tags: [labs]
+      responses:
+        "200":
+          description: OK
+"#,
+    ));
+    for removed in ["/search", "/labs"] {
+        let mut new = old.clone();
+        new.paths.paths.shift_remove(removed);
+        let changes = diff_specs(&old, &new);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(
+            changes[0].to_string(),
+            format!("{removed} - endpoint removed")
+        );
+        assert_eq!(changes[0].severity, Severity::Unstable);
+    }
+
+    let stable = parse_spec(&minimal_spec(
+        r#"
+tags:
+  - name: labs
+    x-stability: stable
+paths:
+  /search:
+    get:
+      x-stability: stable
+      responses:
+        "200":
+          description: OK
+  /labs:
+    get:
+      tags: [labs]
+      responses:
+        "200":
+          description: OK
+"#,
+    ));
+    let mut new = stable.clone();
+    new.paths.paths.shift_remove("/search");
+    let changes = diff_specs(&stable, &new);
+    assert!(changes.has_breaking());
+}
+
+#[test]
+fn dedup_keeps_the_message_of_the_worst_copy() {
+    let loc = Location::Path("/pets".into());
+    let change = |severity, message: &str| {
+        Change::new(severity, loc.clone(), message)
+            .with_event(Event::new(&loc, EventKind::PathRemoved))
+    };
+    let changes = dedup(vec![
+        change(Severity::NonBreaking, "endpoint removed (was deprecated)"),
+        change(Severity::Breaking, "endpoint removed"),
+    ]);
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].severity, Severity::Breaking);
+    assert_eq!(changes[0].message, "endpoint removed");
+}
+
+#[test]
+fn removing_deprecated_elements_is_breaking_by_default() {
+    let old = parse_spec(&minimal_spec(
+        r#"
+paths:
+  /pets:
+    get:
+      parameters:
+        - name: limit
+          in: query
+          schema:
+            type: integer
+        - name: legacy
+          in: query
+          required: true
+          deprecated: true
+          schema:
+            type: string
+      responses:
+        "200":
+          description: OK
+          content:
+            application/json:
+              schema:
+                type: object
+                properties:
+                  name:
+                    type: string
+                  nickname:
+                    type: string
+                    deprecated: true
+    delete:
+      deprecated: true
+      x-sunset: "2024-06-30"
+      responses:
+        "204":
+          description: Deleted
+"#,
+    ));
+    let new = parse_spec(&minimal_spec(
+        r#"
+paths:
+  /pets:
+    get:
+      parameters:
+        - name: limit
+          in: query
+          schema:
+            type: integer
+      responses:
+        "200":
+          description: OK
+          content:
+            application/json:
+              schema:
+                type: object
+                properties:
+                  name:
+                    type: string
+"#,
+    ));
+    let changes = diff_specs(&old, &new);
+    assert_eq!(changes.breaking().len(), 3);
+}
+
+#[test]
+fn removal_policy_allows_deprecated_elements() {
+    let old = parse_spec(&minimal_spec(
+        r#"
+paths:
+  /pets:
+    get:
+      parameters:
+        - name: limit
+          in: query
+          schema:
+            type: integer
+        - name: legacy
+          in: query
+          required: true
+          deprecated: true
+          schema:
+            type: string
+      responses:
+        "200":
+          description: OK
+          content:
+            application/json:
+              schema:
+                type: object
+                properties:
+                  name:
+                    type: string
+                  nickname:
+                    type: string
+                    deprecated: true
+    delete:
+      deprecated: true
+      x-sunset: "2024-06-30"
+      responses:
+        "204":
+          description: Deleted
+"#,
+    ));
+    let new = parse_spec(&minimal_spec(
+        r#"
+paths:
+  /pets:
+    get:
+      parameters:
+        - name: limit
+          in: query
+          schema:
+            type: integer
+      responses:
+        "200":
+          description: OK
+          content:
+            application/json:
+              schema:
+                type: object
+                properties:
+                  name:
+                    type: string
+"#,
+    ));
+    let options = DiffOptions::new().removal_policy(RemovalPolicy::AllowDeprecated);
+    let changes = diff_specs_with(&old, &new, &options);
+    assert_eq!(changes.len(), 3);
+    assert!(!changes.has_breaking());
+    assert!(changes[0].message.ends_with("(was deprecated)"));
+}
+
+#[test]
+fn removal_policy_applies_to_paths_of_deprecated_operations() {
+    let removed = parse_spec(&minimal_spec("paths: {}"));
+    let sunset = |today: &str| {
+        DiffOptions::new().removal_policy(RemovalPolicy::AfterSunset {
+            today: today.into(),
+        })
+    };
+    let allow = DiffOptions::new().removal_policy(RemovalPolicy::AllowDeprecated);
+    let old = parse_spec(&minimal_spec(
+        r#"
+paths:
+  /legacy:
+    get:
+      deprecated: true
+      x-sunset: "2024-06-30"
+      responses:
+        "200":
+          description: OK
+    post:
+      deprecated: true
+      x-sunset: "2024-09-30"
+      responses:
+        "201":
+          description: Created
+"#,
+    ));
+
+    assert!(diff_specs(&old, &removed).has_breaking());
+    let allowed = diff_specs_with(&old, &removed, &allow);
+    assert!(!allowed.has_breaking());
+    assert_eq!(allowed[0].message, "endpoint removed (was deprecated)");
+    // The path can only go once every operation's sunset date has passed.
+    assert!(diff_specs_with(&old, &removed, &sunset("2024-07-01")).has_breaking());
+    assert!(!diff_specs_with(&old, &removed, &sunset("2024-10-01")).has_breaking());
+
+    let partly = parse_spec(&minimal_spec(
+        r#"
+paths:
+  /legacy:
+    get:
+      deprecated: true
+      x-sunset: "2024-06-30"
+      responses:
+        "200":
+          description: OK
+    post:
+      deprecated: false
+      x-sunset: "2024-09-30"
+      responses:
+        "201":
+          description: Created
+"#,
+    ));
+    assert!(diff_specs_with(&partly, &removed, &allow).has_breaking());
+}
+
+#[test]
+fn removal_policy_requires_passed_sunset() {
+    let old = parse_spec(&minimal_spec(
+        r#"
+paths:
+  /pets:
+    get:
+      parameters:
+        - name: limit
+          in: query
+          schema:
+            type: integer
+        - name: legacy
+          in: query
+          required: true
+          deprecated: true
+          schema:
+            type: string
+      responses:
+        "200":
+          description: OK
+          content:
+            application/json:
+              schema:
+                type: object
+                properties:
+                  name:
+                    type: string
+                  nickname:
+                    type: string
+                    deprecated: true
+    delete:
+      deprecated: true
+      x-sunset: "2024-06-30"
+      responses:
+        "204":
+          description: Deleted
+"#,
+    ));
+    let new = parse_spec(&minimal_spec(
+        r#"
+paths:
+  /pets:
+    get:
+      parameters:
+        - name: limit
+          in: query
+          schema:
+            type: integer
+      responses:
+        "200":
+          description: OK
+          content:
+            application/json:
+              schema:
+                type: object
+                properties:
+                  name:
+                    type: string
+"#,
+    ));
+    let policy = |today: &str| {
+        DiffOptions::new().removal_policy(RemovalPolicy::AfterSunset {
+            today: today.into(),
+        })
+    };
+    let before = diff_specs_with(&old, &new, &policy("2024-06-29"));
+    assert_eq!(before.breaking().len(), 3);
+
+    // Only the operation carries an `x-sunset` date.
+    let after = diff_specs_with(&old, &new, &policy("2024-07-01"));
+    assert_eq!(after.breaking().len(), 2);
+    assert!(
+        after.non_breaking()[0]
+            .message
+            .contains("operation removed")
+    );
+}
+
+#[test]
+fn newly_deprecated_members_are_reported_at_their_location() {
+    let old = parse_spec(&minimal_spec(
+        r##"
+paths:
+  /pets:
+    get:
+      parameters:
+        - name: limit
+          in: query
+          schema:
+            type: integer
+      responses:
+        "200":
+          description: OK
+          content:
+            application/json:
+              schema:
+                type: object
+                properties:
+                  name:
+                    type: string
+                  owner:
+                    $ref: "#/components/schemas/Owner"
+components:
+  schemas:
+    Owner:
+      type: object
+"##,
+    ));
+    let new = parse_spec(&minimal_spec(
+        r##"
+paths:
+  /pets:
+    get:
+      parameters:
+        - name: limit
+          in: query
+          deprecated: true
+          schema:
+            type: integer
+      responses:
+        "200":
+          description: OK
+          content:
+            application/json:
+              schema:
+                type: object
+                properties:
+                  name:
+                    type: string
+                    deprecated: true
+                  owner:
+                    $ref: "#/components/schemas/Owner"
+components:
+  schemas:
+    Owner:
+      type: object
+      deprecated: true
+"##,
+    ));
+    let changes = diff_specs(&old, &new);
+    let messages: Vec<String> = changes
+        .non_breaking()
+        .iter()
+        .map(|c| c.to_string())
+        .collect();
+    assert_eq!(
+        messages,
+        [
+            "GET /pets - query parameter 'limit': deprecated",
+            "GET /pets - response '200' application/json.name: deprecated",
+            "component Owner - schema deprecated",
+        ]
+    );
+    assert!(!changes.has_breaking());
+}
+
+#[test]
+fn undeprecation_is_reported() {
+    let old = parse_spec(&minimal_spec(
+        r##"
+paths:
+  /pets:
+    get:
+      parameters:
+        - name: limit
+          in: query
+          deprecated: true
+          schema:
+            type: integer
+      responses:
+        "200":
+          description: OK
+          content:
+            application/json:
+              schema:
+                type: object
+                properties:
+                  name:
+                    type: string
+                    deprecated: true
+                  owner:
+                    $ref: "#/components/schemas/Owner"
+components:
+  schemas:
+    Owner:
+      type: object
+      deprecated: true
+"##,
+    ));
+    let new = parse_spec(&minimal_spec(
+        r##"
+paths:
+  /pets:
+    get:
+      parameters:
+        - name: limit
+          in: query
+          schema:
+            type: integer
+      responses:
+        "200":
+          description: OK
+          content:
+            application/json:
+              schema:
+                type: object
+                properties:
+                  name:
+                    type: string
+                  owner:
+                    $ref: "#/components/schemas/Owner"
+components:
+  schemas:
+    Owner:
+      type: object
+"##,
+    ));
+    let changes = diff_specs(&old, &new);
+    assert_eq!(changes.len(), 3);
+    assert!(
+        changes
+            .non_breaking()
+            .iter()
+            .all(|c| c.message.ends_with("no longer deprecated"))
+    );
+}
+
+#[test]
+fn documentation_changes_are_info() {
+    let old = parse_spec(&minimal_spec(
+        r#"
+paths:
+  /pets:
+    get:
+      summary: List pets
+      tags: [pets]
+      parameters:
+        - name: limit
+          in: query
+          example: 10
+          schema:
+            type: integer
+      responses:
+        "200":
+          description: OK
+          content:
+            application/json:
+              schema:
+                type: string
+                enum: [cat, dog]
+                description: Species
+"#,
+    ));
+    let new = parse_spec(&minimal_spec(
+        r#"
+paths:
+  /pets:
+    get:
+      summary: List all pets
+      tags: [animals]
+      parameters:
+        - name: limit
+          in: query
+          example: 20
+          schema:
+            type: integer
+      responses:
+        "200":
+          description: OK
+          content:
+            application/json:
+              schema:
+                type: string
+                enum: [cat, dog]
+                description: Species
+"#,
+    ));
+    let changes = diff_specs(&old, &new);
+    let messages: Vec<&str> = changes.info().iter().map(|c| c.message.as_str()).collect();
+    assert_eq!(
+        messages,
+        [
+            "summary changed",
+            "tag 'pets' removed",
+            "tag 'animals' added",
+            "example changed",
+        ]
+    );
+    assert_eq!(changes.len(), 4);
+    assert!(changes.fails_on(Severity::Info));
+    assert!(!changes.fails_on(Severity::NonBreaking));
+}
+
+#[test]
+fn tolerant_reader_downgrades_response_enum_additions_to_warning() {
+    let old = parse_spec(&minimal_spec(
+        r#"
+paths:
+  /pets:
+    get:
+      summary: List pets
+      tags: [pets]
+      parameters:
+        - name: limit
+          in: query
+          example: 10
+          schema:
+            type: integer
+      responses:
+        "200":
+          description: OK
+          content:
+            application/json:
+              schema:
+                type: string
+                enum: [cat, dog]
+                description: Species
+"#,
+    ));
+    let new = parse_spec(&minimal_spec(
+        r#"
+paths:
+  /pets:
+    get:
+      summary: List pets
+      tags: [pets]
+      parameters:
+        - name: limit
+          in: query
+          example: 10
+          schema:
+            type: integer
+      responses:
+        "200":
+          description: OK
+          content:
+            application/json:
+              schema:
+                type: string
+                enum: [cat, dog, bird]
+                description: Species
+"#,
+    ));
+    assert!(diff_specs(&old, &new).has_breaking());
+
+    let options = DiffOptions::new().tolerant_reader(true);
+    let changes = diff_specs_with(&old, &new, &options);
+    assert_eq!(changes.warnings().len(), 1);
+    assert!(!changes.fails_on(Severity::Breaking));
+    assert!(changes.fails_on(Severity::Warning));
+}
+
+#[test]
+fn changes_carry_structured_locations() {
+    let old = parse_spec(&minimal_spec(
+        r#"
+paths:
+  /pets:
+    get:
+      security:
+        - oauth: [read]
+      parameters:
+        - name: limit
+          in: query
+          required: false
+          schema:
+            type: integer
+      responses:
+        "200":
+          description: OK
+          headers:
+            X-Rate-Limit:
+              required: true
+              schema:
+                type: integer
+            X-Request-Id:
+              schema:
+                type: string
+          content:
+            application/json:
+              schema:
+                type: array
+                items:
+                  type: object
+                  properties:
+                    owner:
+                      type: object
+                      properties:
+                        name:
+                          type: string
+"#,
+    ));
+    let new = parse_spec(&minimal_spec(
+        r#"
+paths:
+  /pets:
+    get:
+      security:
+        - oauth: [read]
+      parameters:
+        - name: limit
+          in: query
+          required: true
+          schema:
+            type: integer
+      responses:
+        "200":
+          description: OK
+          headers:
+            X-Rate-Limit:
+              required: true
+              schema:
+                type: integer
+            X-Request-Id:
+              schema:
+                type: string
+          content:
+            application/json:
+              schema:
+                type: array
+                items:
+                  type: object
+                  properties:
+                    owner:
+                      type: object
+                      properties:
+                        name:
+                          type: string
+"#,
+    ));
+    let changes = diff_specs(&old, &new);
+    let op = Location::Operation {
+        path: "/pets".into(),
+        method: "GET".into(),
+    };
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].location, op.parameter("limit", "query"));
+    assert_eq!(changes[0].location.scope(), &op);
+    assert_eq!(
+        changes[0].to_string(),
+        "GET /pets - query parameter 'limit': became required"
+    );
+
+    let schema = op
+        .response("200")
+        .media_type("application/json")
+        .items()
+        .property("owner")
+        .property("name");
+    assert_eq!(
+        schema,
+        Location::Schema {
+            parent: Box::new(op.response("200").media_type("application/json")),
+            path: "[].owner.name".into(),
+        }
+    );
+    assert_eq!(
+        schema.to_string(),
+        "GET /pets response '200' application/json[].owner.name"
+    );
+}
+
+#[test]
+fn additions_and_removals_are_located_at_the_element() {
+    let old = parse_spec(&minimal_spec(
+        r#"
+paths:
+  /pets:
+    get:
+      parameters:
+        - name: limit
+          in: query
+          schema:
+            type: integer
+      responses:
+        "200":
+          description: OK
+          headers:
+            X-Rate-Limit:
+              schema:
+                type: integer
+          content:
+            application/json:
+              schema:
+                type: object
+                properties:
+                  name:
+                    type: string
+"#,
+    ));
+    let new = parse_spec(&minimal_spec(
+        r#"
+paths:
+  /pets:
+    get:
+      responses:
+        "200":
+          description: OK
+          content:
+            application/json:
+              schema:
+                type: object
+                properties:
+                  name:
+                    type: string
+                  tag:
+                    type: string
+            application/xml: {}
+        "404":
+          description: Missing
+"#,
+    ));
+    let changes = diff_specs(&old, &new);
+    let op = Location::Operation {
+        path: "/pets".into(),
+        method: "GET".into(),
+    };
+    let ok = op.response("200");
+    let locations: Vec<&Location> = changes.changes().iter().map(|c| &c.location).collect();
+    assert_eq!(
+        locations,
+        [
+            &op.parameter("limit", "query"),
+            &ok.header("X-Rate-Limit"),
+            &ok.media_type("application/json").property("tag"),
+            &ok.media_type("application/xml"),
+            &op.response("404"),
+        ]
+    );
+    assert_eq!(changes[0].location.parent(), Some(&op));
+    assert_eq!(
+        changes[0].to_string(),
+        "GET /pets - query parameter 'limit': removed"
+    );
+    let limit = changes
+        .changes()
+        .iter()
+        .filter(|c| matches!(&c.location, Location::Parameter { name, .. } if name == "limit"));
+    assert_eq!(limit.count(), 1);
+}
+
+#[test]
+fn response_header_changes() {
+    let old = parse_spec(&minimal_spec(
+        r#"
+paths:
+  /pets:
+    get:
+      security:
+        - oauth: [read]
+      parameters:
+        - name: limit
+          in: query
+          required: false
+          schema:
+            type: integer
+      responses:
+        "200":
+          description: OK
+          headers:
+            X-Rate-Limit:
+              required: true
+              schema:
+                type: integer
+            X-Request-Id:
+              schema:
+                type: string
+          content:
+            application/json:
+              schema:
+                type: array
+                items:
+                  type: object
+                  properties:
+                    owner:
+                      type: object
+                      properties:
+                        name:
+                          type: string
+"#,
+    ));
+    let new = parse_spec(&minimal_spec(
+        r#"
+paths:
+  /pets:
+    get:
+      security:
+        - oauth: [read]
+      parameters:
+        - name: limit
+          in: query
+          required: false
+          schema:
+            type: integer
+      responses:
+        "200":
+          description: OK
+          headers:
+            X-Request-Id:
+              schema:
+                type: string
+          content:
+            application/json:
+              schema:
+                type: array
+                items:
+                  type: object
+                  properties:
+                    owner:
+                      type: object
+                      properties:
+                        name:
+                          type: string
+"#,
+    ));
+    let changes = diff_specs(&old, &new);
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].severity, Severity::Breaking);
+    assert_eq!(
+        changes[0].to_string(),
+        "GET /pets - response '200' header 'X-Rate-Limit': removed"
+    );
+
+    let added = diff_specs(&new, &old);
+    assert!(!added.has_breaking());
+}
+
+#[test]
+fn security_scope_changes() {
+    let old = parse_spec(&minimal_spec(
+        r#"
+paths:
+  /pets:
+    get:
+      security:
+        - oauth: [read]
+      parameters:
+        - name: limit
+          in: query
+          required: false
+          schema:
+            type: integer
+      responses:
+        "200":
+          description: OK
+          headers:
+            X-Request-Id:
+              schema:
+                type: string
+          content:
+            application/json:
+              schema:
+                type: array
+                items:
+                  type: object
+                  properties:
+                    owner:
+                      type: object
+                      properties:
+                        name:
+                          type: string
+"#,
+    ));
+    let new = parse_spec(&minimal_spec(
+        r#"
+paths:
+  /pets:
+    get:
+      security:
+        - oauth: [read, write]
+      parameters:
+        - name: limit
+          in: query
+          required: false
+          schema:
+            type: integer
+      responses:
+        "200":
+          description: OK
+          headers:
+            X-Request-Id:
+              schema:
+                type: string
+          content:
+            application/json:
+              schema:
+                type: array
+                items:
+                  type: object
+                  properties:
+                    owner:
+                      type: object
+                      properties:
+                        name:
+                          type: string
+"#,
+    ));
+    let changes = diff_specs(&old, &new);
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].severity, Severity::Breaking);
+    assert_eq!(
+        changes[0].to_string(),
+        "GET /pets - security scheme 'oauth': scope 'write' added"
+    );
+    assert!(!diff_specs(&new, &old).has_breaking());
+}
+
+#[test]
+fn dropping_authentication_is_not_breaking() {
+    let none = parse_spec(&minimal_spec(
+        r#"
+paths:
+  /pets:
+    get:
+      security: []
+      responses:
+        "200":
+          description: OK
+"#,
+    ));
+    let key = parse_spec(&minimal_spec(
+        r#"
+paths:
+  /pets:
+    get:
+      security: [{key: []}]
+      responses:
+        "200":
+          description: OK
+"#,
+    ));
+    let anonymous = parse_spec(&minimal_spec(
+        r#"
+paths:
+  /pets:
+    get:
+      security: [{}]
+      responses:
+        "200":
+          description: OK
+"#,
+    ));
+    let token = parse_spec(&minimal_spec(
+        r#"
+paths:
+  /pets:
+    get:
+      security: [{token: []}]
+      responses:
+        "200":
+          description: OK
+"#,
+    ));
+    let rendered = |old: &OpenAPI, new: &OpenAPI| -> Vec<String> {
+        diff_specs(old, new)
+            .changes()
+            .iter()
+            .map(|c| format!("[{}] {}", c.severity, c.message))
+            .collect()
+    };
+    assert_eq!(
+        rendered(&key, &none),
+        ["[non-breaking] security requirement 'key' removed"]
+    );
+    assert_eq!(
+        rendered(&key, &anonymous),
+        [
+            "[non-breaking] security requirement 'key' removed",
+            "[non-breaking] security requirement 'anonymous' added",
+        ]
+    );
+    assert_eq!(
+        rendered(&key, &token),
+        [
+            "[breaking] security requirement 'key' removed",
+            "[non-breaking] security requirement 'token' added",
+        ]
+    );
+    assert_eq!(
+        rendered(&none, &key),
+        ["[breaking] security requirement 'key' added"]
+    );
+}
+
+#[test]
+fn changes_are_ordered_by_spec_structure() {
+    let old = parse_spec(&minimal_spec(
+        r#"
+paths:
+  /zebras:
+    get:
+      parameters:
+        - name: z
+          in: query
+          schema:
+            type: string
+        - name: a
+          in: query
+          schema:
+            type: string
+        - name: b
+          in: query
+          schema:
+            type: string
+        - name: c
+          in: query
+          schema:
+            type: string
+        - name: d
+          in: query
+          schema:
+            type: string
+      responses:
+        "200":
+          description: OK
+  /pets:
+    get:
+      parameters:
+        - name: p
+          in: query
+          schema:
+            type: string
+        - name: a
+          in: query
+          schema:
+            type: string
+        - name: b
+          in: query
+          schema:
+            type: string
+        - name: c
+          in: query
+          schema:
+            type: string
+        - name: d
+          in: query
+          schema:
+            type: string
+      responses:
+        "200":
+          description: OK
+          content:
+            application/json:
+              schema:
+                type: string
+                enum: [s1, s2, s3, s4]
+"#,
+    ));
+    let new = parse_spec(&minimal_spec(
+        r#"
+paths:
+  /zebras:
+    get:
+      parameters:
+        - name: z
+          in: query
+          schema:
+            type: string
+      responses:
+        "200":
+          description: OK
+  /pets:
+    get:
+      parameters:
+        - name: p
+          in: query
+          schema:
+            type: string
+      responses:
+        "200":
+          description: OK
+          content:
+            application/json:
+              schema:
+                type: string
+                enum: [s0]
+"#,
+    ));
+    let rendered = |changes: &Diff| -> Vec<String> {
+        (0..changes.len()).map(|i| changes[i].to_string()).collect()
+    };
+    let changes = rendered(&diff_specs(&old, &new));
+    for _ in 0..5 {
+        assert_eq!(rendered(&diff_specs(&old, &new)), changes);
+    }
+    assert_eq!(
+        changes,
+        [
+            "GET /pets - query parameter 'a': removed",
+            "GET /pets - query parameter 'b': removed",
+            "GET /pets - query parameter 'c': removed",
+            "GET /pets - query parameter 'd': removed",
+            "GET /pets - response '200' application/json: enum value 's1' removed",
+            "GET /pets - response '200' application/json: enum value 's2' removed",
+            "GET /pets - response '200' application/json: enum value 's3' removed",
+            "GET /pets - response '200' application/json: enum value 's4' removed",
+            "GET /pets - response '200' application/json: enum value 's0' added",
+            "GET /zebras - query parameter 'a': removed",
+            "GET /zebras - query parameter 'b': removed",
+            "GET /zebras - query parameter 'c': removed",
+            "GET /zebras - query parameter 'd': removed",
+        ]
+    );
+}
+
+#[test]
+fn additions_and_removals_sort_within_their_section() {
+    let old = parse_spec(&minimal_spec(
+        r#"
+paths:
+  /pets/{petId}:
+    get:
+      parameters:
+        - name: petId
+          in: path
+          required: true
+          schema:
+            type: string
+      responses:
+        "200":
+          description: OK
+          content:
+            application/json:
+              schema:
+                type: object
+                properties:
+                  owner:
+                    type: object
+                    properties:
+                      name:
+                        type: string
+                  owner-id:
+                    type: string
+"#,
+    ));
+    let new = parse_spec(&minimal_spec(
+        r#"
+paths:
+  /pets/{petId}:
+    get:
+      parameters:
+        - name: petId
+          in: path
+          required: true
+          schema:
+            type: integer
+      responses:
+        "200":
+          description: OK
+          content:
+            application/json:
+              schema:
+                type: object
+                properties:
+                  owner:
+                    type: object
+                    description: Who adopted the pet
+                    properties:
+                      name:
+                        type: integer
+        "404":
+          description: Not found
+"#,
+    ));
+    let changes: Vec<String> = diff_specs(&old, &new)
+        .changes()
+        .iter()
+        .map(|c| c.describe())
+        .collect();
+    assert_eq!(
+        changes,
+        [
+            "path parameter 'petId': type changed from string to integer",
+            "response '200' application/json.owner: description added",
+            "response '200' application/json.owner.name: type changed from string to integer",
+            "response '200' application/json.owner-id: removed",
+            "response '404': added",
+        ]
+    );
+}
+
+#[test]
+fn fingerprints_ignore_severity() {
+    let old = parse_spec(&minimal_spec(
+        r#"
+paths:
+  /zebras:
+    get:
+      parameters:
+        - name: z
+          in: query
+          schema:
+            type: string
+        - name: a
+          in: query
+          schema:
+            type: string
+      responses:
+        "200":
+          description: OK
+  /pets:
+    get:
+      parameters:
+        - name: p
+          in: query
+          schema:
+            type: string
+        - name: a
+          in: query
+          schema:
+            type: string
+      responses:
+        "200":
+          description: OK
+          content:
+            application/json:
+              schema:
+                type: string
+                enum: [s1]
+"#,
+    ));
+    let new = parse_spec(&minimal_spec(
+        r#"
+paths:
+  /zebras:
+    get:
+      parameters:
+        - name: z
+          in: query
+          schema:
+            type: string
+      responses:
+        "200":
+          description: OK
+  /pets:
+    get:
+      parameters:
+        - name: p
+          in: query
+          schema:
+            type: string
+      responses:
+        "200":
+          description: OK
+          content:
+            application/json:
+              schema:
+                type: string
+                enum: [s1]
+"#,
+    ));
+    let removed = &diff_specs(&old, &new)[0];
+    let mut relaxed = removed.clone();
+    relaxed.severity = Severity::Info;
+    assert_eq!(removed.fingerprint(), relaxed.fingerprint());
+    assert_eq!(removed.fingerprint().len(), 16);
+    relaxed.message = "query parameter 'b' removed".into();
+    assert_eq!(removed.fingerprint(), relaxed.fingerprint());
+    relaxed.event = None;
+    assert_ne!(removed.fingerprint(), relaxed.fingerprint());
+}
+
+#[test]
+fn fingerprints_are_pinned_and_ignore_policy_explanations() {
+    let old = parse_spec(&minimal_spec(
+        r#"
+paths:
+  /pets:
+    get:
+      parameters:
+        - name: limit
+          in: query
+          schema:
+            type: integer
+        - name: legacy
+          in: query
+          required: true
+          deprecated: true
+          schema:
+            type: string
+      responses:
+        "200":
+          description: OK
+          content:
+            application/json:
+              schema:
+                type: object
+                properties:
+                  name:
+                    type: string
+                  nickname:
+                    type: string
+                    deprecated: true
+    delete:
+      deprecated: true
+      x-sunset: "2024-06-30"
+      responses:
+        "204":
+          description: Deleted
+"#,
+    ));
+    let new = parse_spec(&minimal_spec(
+        r#"
+paths:
+  /pets:
+    get:
+      parameters:
+        - name: limit
+          in: query
+          schema:
+            type: integer
+      responses:
+        "200":
+          description: OK
+          content:
+            application/json:
+              schema:
+                type: object
+                properties:
+                  name:
+                    type: string
+"#,
+    ));
+    let strict = diff_specs(&old, &new);
+    let options = DiffOptions::new().removal_policy(RemovalPolicy::AllowDeprecated);
+    let allowed = diff_specs_with(&old, &new, &options);
+    assert_eq!(
+        strict[0].location.canonical(),
+        "operation:GET /pets/parameter:query:legacy"
+    );
+    assert_eq!(strict[0].message, "removed");
+    assert!(allowed[0].message.ends_with("(was deprecated)"));
+    // Saved baselines depend on this value: change it only on purpose.
+    assert_eq!(strict[0].fingerprint(), "1fe31084f3a33ef0");
+    assert_eq!(allowed[0].fingerprint(), strict[0].fingerprint());
+}
+
+/// Company rule: renaming a parameter by changing its case is breaking.
+struct ParameterCase;
+
+impl Rule for ParameterCase {
+    fn name(&self) -> &str {
+        "parameter-case"
+    }
+
+    fn classify(&self, event: &Event, cx: &RuleContext) -> Option<Severity> {
+        let EventKind::ParameterAdded { name, r#in, .. } = &event.kind else {
+            return None;
+        };
+        let renamed = cx.events.iter().any(|other| {
+            matches!(&other.kind, EventKind::ParameterRemoved { name: old, r#in: i, .. }
+                if other.location.parent() == event.location.parent() && i == r#in && old.eq_ignore_ascii_case(name))
+        });
+        renamed.then_some(Severity::Breaking)
+    }
+}
+
+/// Replacement for the built-in `enums` rule that never fails the build.
+struct LenientEnums;
+
+impl Rule for LenientEnums {
+    fn name(&self) -> &str {
+        "enums"
+    }
+
+    fn classify(&self, event: &Event, _: &RuleContext) -> Option<Severity> {
+        match event.kind {
+            EventKind::EnumValueAdded { .. } | EventKind::EnumValueRemoved { .. } => {
+                Some(Severity::Warning)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[test]
+fn custom_rules_take_precedence_over_builtins() {
+    let old = parse_spec(&minimal_spec(
+        r#"
+paths:
+  /zebras:
+    get:
+      parameters:
+        - name: z
+          in: query
+          schema:
+            type: string
+        - name: Limit
+          in: query
+          schema:
+            type: string
+      responses:
+        "200":
+          description: OK
+  /pets:
+    get:
+      parameters:
+        - name: p
+          in: query
+          schema:
+            type: string
+        - name: Limit
+          in: query
+          schema:
+            type: string
+      responses:
+        "200":
+          description: OK
+          content:
+            application/json:
+              schema:
+                type: string
+                enum: [s1]
+"#,
+    ));
+    let new = parse_spec(&minimal_spec(
+        r#"
+paths:
+  /zebras:
+    get:
+      parameters:
+        - name: z
+          in: query
+          schema:
+            type: string
+        - name: limit
+          in: query
+          schema:
+            type: string
+      responses:
+        "200":
+          description: OK
+  /pets:
+    get:
+      parameters:
+        - name: p
+          in: query
+          schema:
+            type: string
+        - name: limit
+          in: query
+          schema:
+            type: string
+      responses:
+        "200":
+          description: OK
+          content:
+            application/json:
+              schema:
+                type: string
+                enum: [s1]
+"#,
+    ));
+    assert!(!diff_specs(&old, &new).has_breaking());
+
+    let options = DiffOptions::new().rules(Rules::default().with(ParameterCase));
+    let changes = diff_specs_with(&old, &new, &options);
+    let breaking: Vec<String> = changes.breaking().iter().map(|c| c.to_string()).collect();
+    assert_eq!(
+        breaking,
+        [
+            "GET /pets - query parameter 'limit': added",
+            "GET /zebras - query parameter 'limit': added",
+        ]
+    );
+}
+
+#[test]
+fn builtin_rules_can_be_replaced_or_disabled() {
+    let old = parse_spec(&minimal_spec(
+        r#"
+paths:
+  /zebras:
+    get:
+      parameters:
+        - name: z
+          in: query
+          schema:
+            type: string
+      responses:
+        "200":
+          description: OK
+  /pets:
+    get:
+      parameters:
+        - name: p
+          in: query
+          schema:
+            type: string
+      responses:
+        "200":
+          description: OK
+          content:
+            application/json:
+              schema:
+                type: string
+                enum: [s1]
+"#,
+    ));
+    let new = parse_spec(&minimal_spec(
+        r#"
+paths:
+  /zebras:
+    get:
+      parameters:
+        - name: z
+          in: query
+          schema:
+            type: string
+        - name: a
+          in: query
+          schema:
+            type: string
+      responses:
+        "200":
+          description: OK
+  /pets:
+    get:
+      parameters:
+        - name: p
+          in: query
+          schema:
+            type: string
+        - name: a
+          in: query
+          schema:
+            type: string
+      responses:
+        "200":
+          description: OK
+          content:
+            application/json:
+              schema:
+                type: string
+                enum: [s2]
+"#,
+    ));
+
+    let replaced = DiffOptions::new().rules(Rules::default().with(LenientEnums));
+    let changes = diff_specs_with(&old, &new, &replaced);
+    assert_eq!(changes.warnings().len(), 2);
+    assert!(!changes.has_breaking());
+    assert_eq!(Rules::default().with(LenientEnums).names().count(), 15);
+
+    let disabled = DiffOptions::new().rules(Rules::default().without("enums"));
+    let changes = diff_specs_with(&old, &new, &disabled);
+    assert_eq!(changes.len(), 2);
+    assert!(
+        changes
+            .changes()
+            .iter()
+            .all(|c| c.to_string().ends_with("query parameter 'a': added"))
+    );
+
+    assert!(diff_specs_with(&old, &new, &DiffOptions::new().rules(Rules::empty())).is_empty());
+}
+
+/// Defers to the rules after it, like a script reading `event.severity`,
+/// counting how often it is run.
+struct Deferring {
+    name: String,
+    runs: Arc<AtomicUsize>,
+}
+
+impl Deferring {
+    fn verdict(&self, event: &Event, cx: &RuleContext) -> Option<Severity> {
+        cx.memo(|| {
+            self.runs.fetch_add(1, Ordering::Relaxed);
+            cx.fallback(event).map(|(_, severity)| severity)
+        })
+    }
+}
+
+impl Rule for Deferring {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn classify(&self, event: &Event, cx: &RuleContext) -> Option<Severity> {
+        self.verdict(event, cx)
+    }
+
+    fn findings(&self, event: &Event, cx: &RuleContext) -> Vec<Change> {
+        self.verdict(event, cx);
+        Vec::new()
+    }
+}
+
+#[test]
+fn each_rule_runs_once_per_event() {
+    let runs = Arc::new(AtomicUsize::new(0));
+    let rules = (0..24).fold(Rules::default(), |rules, i| {
+        rules.with(Deferring {
+            name: format!("deferring-{i}"),
+            runs: runs.clone(),
+        })
+    });
+    let old = parse_spec(&minimal_spec(
+        r#"
+paths:
+  /zebras:
+    get:
+      parameters:
+        - name: z
+          in: query
+          schema:
+            type: string
+        - name: a
+          in: query
+          schema:
+            type: string
+        - name: b
+          in: query
+          schema:
+            type: string
+      responses:
+        "200":
+          description: OK
+  /pets:
+    get:
+      parameters:
+        - name: p
+          in: query
+          schema:
+            type: string
+        - name: a
+          in: query
+          schema:
+            type: string
+        - name: b
+          in: query
+          schema:
+            type: string
+      responses:
+        "200":
+          description: OK
+          content:
+            application/json:
+              schema:
+                type: string
+                enum: [s1]
+"#,
+    ));
+    let new = parse_spec(&minimal_spec(
+        r#"
+paths:
+  /zebras:
+    get:
+      parameters:
+        - name: z
+          in: query
+          schema:
+            type: string
+      responses:
+        "200":
+          description: OK
+  /pets:
+    get:
+      parameters:
+        - name: p
+          in: query
+          schema:
+            type: string
+      responses:
+        "200":
+          description: OK
+          content:
+            application/json:
+              schema:
+                type: string
+                enum: [s2]
+"#,
+    ));
+    let changes = diff_specs_with(&old, &new, &DiffOptions::new().rules(rules));
+
+    assert_eq!(changes.len(), diff_specs(&old, &new).len());
+    assert_eq!(runs.load(Ordering::Relaxed), 24 * changes.len());
 }
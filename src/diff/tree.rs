@@ -0,0 +1,100 @@
+use super::Diff;
+use crate::change::{Change, Location, Severity, schema_steps};
+
+/// A node in the hierarchical view of a diff: path → operation → section →
+/// media type → schema path, with each change attached to the node of its
+/// location.
+#[derive(Debug, Default)]
+#[non_exhaustive]
+pub struct Node<'a> {
+    /// What the node stands for, e.g. `/pets`, `GET`, `responses` or
+    /// `application/json`. Empty for the root.
+    pub label: String,
+    /// The worst severity of any change at or below this node.
+    pub severity: Option<Severity>,
+    /// Changes located exactly at this node.
+    pub changes: Vec<&'a Change>,
+    /// Nodes below this one, in spec order.
+    pub children: Vec<Node<'a>>,
+}
+
+impl<'a> Node<'a> {
+    /// A node labelled `label`, with no changes or children yet.
+    pub fn new(label: impl Into<String>) -> Self {
+        Node {
+            label: label.into(),
+            ..Default::default()
+        }
+    }
+
+    fn insert(&mut self, segments: &[String], change: &'a Change) {
+        self.severity = Some(
+            self.severity
+                .map_or(change.severity, |s| s.min(change.severity)),
+        );
+        let Some((first, rest)) = segments.split_first() else {
+            self.changes.push(change);
+            return;
+        };
+        let index = match self.children.iter().position(|c| c.label == *first) {
+            Some(i) => i,
+            None => {
+                self.children.push(Node::new(first.clone()));
+                self.children.len() - 1
+            }
+        };
+        self.children[index].insert(rest, change);
+    }
+}
+
+/// Labels of the tree nodes leading to `loc`, from the root down. Details
+/// of an operation are grouped under a section node (`parameters`,
+/// `responses`, ...), and schema paths get a node per property or `[]`.
+fn segments(loc: &Location) -> Vec<String> {
+    let mut labels = loc.parent().map(segments).unwrap_or_default();
+    let own = match loc {
+        Location::Document => vec![],
+        Location::Path(path) => vec![path.clone()],
+        Location::Operation { path, method } => vec![path.clone(), method.clone()],
+        Location::Callback {
+            parent,
+            name,
+            inner,
+        } => {
+            let mut own = segments(parent);
+            own.push(format!("callback {name}"));
+            own.extend(segments(inner));
+            own
+        }
+        Location::Webhook(inner) => {
+            let mut own = vec!["webhooks".into()];
+            own.extend(segments(inner));
+            own
+        }
+        Location::Component(name) => vec!["components".into(), name.clone()],
+        Location::Parameter { name, r#in, .. } => {
+            vec!["parameters".into(), format!("{} parameter '{name}'", r#in)]
+        }
+        Location::RequestBody { .. } => vec!["request body".into()],
+        Location::Response { code, .. } => vec!["responses".into(), format!("'{code}'")],
+        Location::MediaType { media, .. } => vec![media.clone()],
+        Location::Header { name, .. } => vec!["headers".into(), name.clone()],
+        Location::Schema { path, .. } => schema_steps(path).map(str::to_string).collect(),
+        Location::Security { scheme, .. } => vec!["security".into(), scheme.clone()],
+        Location::Server { url, .. } => vec!["servers".into(), url.clone()],
+    };
+    labels.extend(own);
+    labels
+}
+
+impl Diff {
+    /// The changes arranged as a tree following the structure of the spec.
+    /// The root stands for the whole document.
+    pub fn tree(&self) -> Node<'_> {
+        let mut root = Node::default();
+        for change in &self.changes {
+            root.insert(&segments(&change.location), change);
+        }
+        root
+    }
+}
@@ -0,0 +1,38 @@
+//! Compare two OpenAPI 3.0 specifications and classify every change by how
+//! it affects existing clients.
+//!
+//! ```
+//! use apidiff::{Location, Severity, diff_specs, loader};
+//!
+//! let old = loader::parse_str(
+//!     r#"{"openapi": "3.0.3", "info": {"title": "Pets", "version": "1.0.0"},
+//!         "paths": {"/pets": {"get": {"responses": {"200": {"description": "OK"}}}}}}"#,
+//! )?;
+//! let new = loader::parse_str(
+//!     r#"{"openapi": "3.0.3", "info": {"title": "Pets", "version": "1.0.0"}, "paths": {}}"#,
+//! )?;
+//!
+//! let diff = diff_specs(&old, &new);
+//! assert!(diff.has_breaking());
+//! assert_eq!(diff[0].severity, Severity::Breaking);
+//! assert_eq!(diff[0].location, Location::Path("/pets".into()));
+//! assert_eq!(diff[0].to_string(), "/pets - endpoint removed");
+//! # Ok::<(), apidiff::loader::ParseError>(())
+//! ```
+//!
+//! Use [`diff_specs_with`] and [`DiffOptions`] to change how changes are
+//! judged.
+
+#![warn(missing_docs)]
+
+pub mod change;
+pub mod config;
+pub mod diff;
+pub mod loader;
+pub mod plugin;
+pub mod render;
+pub mod report;
+pub mod version;
+
+pub use change::{Change, Location, Severity};
+pub use diff::{Diff, DiffOptions, RemovalPolicy, Rule, Rules, diff_specs, diff_specs_with};
@@ -1,11 +1,24 @@
-use openapiv3::OpenAPI;
+//! Reading OpenAPI specs from YAML or JSON.
+
+use indexmap::IndexMap;
+use openapiv3::{OpenAPI, PathItem};
+use serde::Deserialize;
 use std::fmt;
 use std::path::Path;
 
+use crate::diff::WEBHOOKS;
+
+/// A spec that could not be parsed.
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum ParseError {
+    /// Invalid YAML, or YAML that is not an OpenAPI 3.0 document.
     Yaml(serde_yml::Error),
+    /// Invalid JSON, or JSON that is not an OpenAPI 3.0 document.
     Json(serde_json::Error),
+    /// A `webhooks` map or `x-webhooks` extension, named first, that is
+    /// not a map of path items.
+    Webhooks(&'static str, serde_json::Error),
 }
 
 impl fmt::Display for ParseError {
@@ -13,15 +26,30 @@ impl fmt::Display for ParseError {
         match self {
             ParseError::Yaml(e) => write!(f, "invalid YAML: {e}"),
             ParseError::Json(e) => write!(f, "invalid JSON: {e}"),
+            ParseError::Webhooks(key, e) => write!(f, "invalid {key}: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ParseError::Yaml(e) => Some(e),
+            ParseError::Json(e) => Some(e),
+            ParseError::Webhooks(_, e) => Some(e),
         }
     }
 }
 
 type LoadErrorPath = String;
 
+/// A spec file that could not be loaded.
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum LoadError {
+    /// The file could not be read.
     Io(LoadErrorPath, std::io::Error),
+    /// The file's contents could not be parsed.
     Parse(LoadErrorPath, ParseError),
 }
 
@@ -34,7 +62,14 @@ impl fmt::Display for LoadError {
     }
 }
 
-impl std::error::Error for LoadError {}
+impl std::error::Error for LoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LoadError::Io(_, e) => Some(e),
+            LoadError::Parse(_, e) => Some(e),
+        }
+    }
+}
 
 enum Format {
     Json,
@@ -49,11 +84,39 @@ fn detect_format(path: &Path) -> Option<Format> {
     }
 }
 
+/// The OpenAPI 3.1 `webhooks` map, which the 3.0 model drops.
+#[derive(Deserialize)]
+struct Webhooks {
+    webhooks: Option<serde_json::Value>,
+}
+
 fn parse_content(content: &str, format: Format) -> Result<OpenAPI, ParseError> {
-    match format {
-        Format::Json => serde_json::from_str(content).map_err(ParseError::Json),
-        Format::Yaml => serde_yml::from_str(content).map_err(ParseError::Yaml),
+    let (mut spec, webhooks): (OpenAPI, Webhooks) = match format {
+        Format::Json => serde_json::from_str(content)
+            .and_then(|spec| Ok((spec, serde_json::from_str(content)?)))
+            .map_err(ParseError::Json),
+        Format::Yaml => serde_yml::from_str(content)
+            .and_then(|spec| Ok((spec, serde_yml::from_str(content)?)))
+            .map_err(ParseError::Yaml),
+    }?;
+    // Kept among the extensions, which are written back at the top level,
+    // so the diff and anything serializing the spec still see it.
+    if let Some(webhooks) = webhooks.webhooks {
+        spec.extensions.insert("webhooks".into(), webhooks);
+    }
+    check_webhooks(spec)
+}
+
+/// Reject webhooks the diff could not read, which the OpenAPI model leaves
+/// unchecked as arbitrary extension values.
+fn check_webhooks(spec: OpenAPI) -> Result<OpenAPI, ParseError> {
+    for key in WEBHOOKS {
+        if let Some(webhooks) = spec.extensions.get(key) {
+            serde_json::from_value::<IndexMap<String, PathItem>>(webhooks.clone())
+                .map_err(|e| ParseError::Webhooks(key, e))?;
+        }
     }
+    Ok(spec)
 }
 
 fn parse_unknown_content(content: &str) -> Result<OpenAPI, ParseError> {
@@ -72,6 +135,13 @@ fn parse_content_auto(content: &str, format: Option<Format>) -> Result<OpenAPI,
     }
 }
 
+/// Parse a spec from a string, trying JSON first and then YAML.
+pub fn parse_str(content: &str) -> Result<OpenAPI, ParseError> {
+    parse_unknown_content(content)
+}
+
+/// Load a spec from a file. `.json`, `.yaml` and `.yml` files are parsed
+/// as such; anything else is tried as JSON, then YAML.
 pub fn load_file(path: &Path) -> Result<OpenAPI, LoadError> {
     let path_str = path.display().to_string();
     let content = read_file(path)?;
@@ -143,3 +143,25 @@ fn parse_error_display() {
     let msg = err.to_string();
     assert!(msg.contains("invalid JSON"));
 }
+
+#[test]
+fn malformed_webhooks_are_rejected() {
+    let spec = format!("{MINIMAL_YAML}x-webhooks:\n  newPet:\n    post: 42\n");
+    let err = parse_str(&spec).unwrap_err();
+    assert!(matches!(err, ParseError::Webhooks("x-webhooks", _)));
+    assert!(err.to_string().starts_with("invalid x-webhooks: "));
+
+    let spec = format!("{MINIMAL_YAML}x-webhooks:\n  newPet:\n    post:\n      responses: {{}}\n");
+    assert!(parse_str(&spec).is_ok());
+
+    let spec = format!("{MINIMAL_YAML}webhooks:\n  newPet:\n    post: 42\n");
+    let err = parse_str(&spec).unwrap_err();
+    assert!(err.to_string().starts_with("invalid webhooks: "));
+}
+
+#[test]
+fn openapi_31_webhooks_are_kept() {
+    let spec = format!("{MINIMAL_YAML}webhooks:\n  newPet:\n    post:\n      responses: {{}}\n");
+    let spec = parse_str(&spec).unwrap();
+    assert!(spec.extensions["webhooks"]["newPet"]["post"].is_object());
+}
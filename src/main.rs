@@ -1,59 +1,416 @@
-mod change;
-mod diff;
-mod loader;
-
-use clap::Parser;
-use std::path::PathBuf;
+use apidiff::config::Config;
+use apidiff::report::Report;
+use apidiff::{Diff, DiffOptions, RemovalPolicy, Severity, loader, plugin, render, version};
+use clap::{Args, CommandFactory, Parser, Subcommand, ValueEnum};
+use openapiv3::OpenAPI;
+use std::ffi::OsString;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, Parser)]
-#[command(version, about, long_about = None)]
+#[command(
+    version,
+    about,
+    long_about = None,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true,
+    after_help = "Plugins:\n  apidiff <NAME> <OLD> <NEW> [OPTIONS] [-- <ARGS>...] runs `apidiff-<NAME>`\n  from PATH with the diff and both specs as JSON on stdin"
+)]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    judging: DiffArgs,
+
+    #[command(flatten)]
+    diff: Option<DiffCli>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Write a Keep-a-Changelog style history of an API from its successive
+    /// specs, newest release first
+    Changelog(ChangelogCli),
+    /// Render a diff saved with `--format json` in another format
+    Render(RenderCli),
+}
+
+/// Diff two specs, the default command. How changes are judged is kept
+/// apart in `Cli`, since clap only fills in an optional flattened struct
+/// without nested ones.
+#[derive(Debug, Args)]
+struct DiffCli {
     old: PathBuf,
     new: PathBuf,
+
+    /// Print the next `info.version` the changes call for
+    #[arg(long)]
+    suggest_version: bool,
+
+    /// Output format
+    #[arg(long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+
+    /// Render the report through a minijinja template instead of a
+    /// built-in format
+    #[arg(long, value_name = "FILE", conflicts_with = "format")]
+    template: Option<PathBuf>,
+
+    /// Least severe level of change that fails with exit code 1
+    #[arg(long, value_enum, default_value_t = FailOn::Breaking)]
+    fail_on: FailOn,
+
+    /// Arguments passed on to a plugin
+    #[arg(last = true)]
+    plugin_args: Vec<String>,
 }
 
-fn run(cli: Cli) -> Result<(), Box<dyn std::error::Error>> {
-    let old_spec = loader::load_file(&cli.old)?;
-    let new_spec = loader::load_file(&cli.new)?;
+#[derive(Debug, Args)]
+struct ChangelogCli {
+    /// Specs in release order, oldest first
+    #[arg(required = true, num_args = 2..)]
+    specs: Vec<PathBuf>,
 
-    let diff = diff::diff_specs(&old_spec, &new_spec);
+    #[command(flatten)]
+    diff: DiffArgs,
+}
 
-    if diff.is_empty() {
-        println!("No changes detected.");
-        return Ok(());
+#[derive(Debug, Args)]
+struct RenderCli {
+    /// Report written by `apidiff --format json`
+    report: PathBuf,
+
+    /// Only show changes that are not in this earlier report, and list the
+    /// ones that have gone away on stderr
+    #[arg(long, value_name = "REPORT")]
+    since: Option<PathBuf>,
+
+    /// Output format
+    #[arg(long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+
+    /// Render the report through a minijinja template instead of a
+    /// built-in format
+    #[arg(long, value_name = "FILE", conflicts_with = "format")]
+    template: Option<PathBuf>,
+}
+
+/// How changes are judged, shared by all commands.
+#[derive(Debug, Args)]
+struct DiffArgs {
+    /// Report changes to shared schema components once, grouped under the
+    /// component with the operations that use it
+    #[arg(long)]
+    group_components: bool,
+
+    /// Treat removing operations, parameters and properties that were already
+    /// deprecated in the old spec as non-breaking
+    #[arg(long)]
+    allow_deprecated_removal: bool,
+
+    /// With --allow-deprecated-removal, also require an `x-sunset` date
+    /// (YYYY-MM-DD) that has passed
+    #[arg(long, requires = "allow_deprecated_removal")]
+    require_sunset: bool,
+
+    /// Assume clients ignore unknown enum values, so adding one to a response
+    /// is a warning instead of breaking
+    #[arg(long)]
+    tolerant_reader: bool,
+
+    /// Configuration file for disabling rules and adding script rules
+    #[arg(long)]
+    config: Option<PathBuf>,
+}
+
+impl DiffArgs {
+    fn options(&self) -> Result<DiffOptions, Box<dyn std::error::Error>> {
+        let config = match &self.config {
+            Some(path) => Config::load(path)?,
+            None => Config::default(),
+        };
+        let removal_policy = match (self.allow_deprecated_removal, self.require_sunset) {
+            (false, _) => RemovalPolicy::Strict,
+            (true, false) => RemovalPolicy::AllowDeprecated,
+            (true, true) => RemovalPolicy::AfterSunset { today: today() },
+        };
+        Ok(DiffOptions::new()
+            .group_components(self.group_components)
+            .removal_policy(removal_policy)
+            .tolerant_reader(self.tolerant_reader)
+            .rules(config.rules()?))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Format {
+    /// Changes grouped by severity
+    Text,
+    /// Changes arranged by spec structure
+    Tree,
+    /// A self-contained HTML report
+    Html,
+    /// A JUnit XML test suite with a test case per operation
+    Junit,
+    /// A Markdown guide to adapting clients to the breaking changes
+    Migration,
+    /// Changes grouped by severity, as Markdown
+    Markdown,
+    /// The diff and both specs as JSON, for `apidiff render` and plugins
+    Json,
+}
+
+impl Format {
+    /// Whether the output is a document of its own, which the version check
+    /// must stay out of.
+    fn is_document(self) -> bool {
+        !matches!(self, Format::Text | Format::Tree)
     }
+}
 
-    let breaking = diff.breaking();
-    let non_breaking = diff.non_breaking();
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum FailOn {
+    Breaking,
+    Warning,
+    NonBreaking,
+    Info,
+}
 
-    if !breaking.is_empty() {
-        println!("Breaking changes ({}):", breaking.len());
-        for c in &breaking {
-            println!("  {c}");
+impl From<FailOn> for Severity {
+    fn from(level: FailOn) -> Self {
+        match level {
+            FailOn::Breaking => Severity::Breaking,
+            FailOn::Warning => Severity::Warning,
+            FailOn::NonBreaking => Severity::NonBreaking,
+            FailOn::Info => Severity::Info,
         }
     }
+}
 
-    if !non_breaking.is_empty() {
-        if !breaking.is_empty() {
-            println!();
-        }
-        println!("Non-breaking changes ({}):", non_breaking.len());
-        for c in &non_breaking {
-            println!("  {c}");
+/// Today's UTC date as `YYYY-MM-DD`.
+fn today() -> String {
+    let secs = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    // Days since 1970-01-01 to a civil date (Howard Hinnant's algorithm).
+    let z = (secs / 86_400) as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// Check the `info.version` bump against the diff, writing any finding and
+/// the suggested version to `out`. Returns whether the check failed.
+fn check_version(
+    cli: &DiffCli,
+    old_spec: &OpenAPI,
+    new_spec: &OpenAPI,
+    diff: &Diff,
+    out: &mut dyn Write,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let old = version::parse(&old_spec.info.version);
+    let new = version::parse(&new_spec.info.version);
+
+    // Specs that don't use semantic versions are only an error when a
+    // suggestion was asked for.
+    let old = match old {
+        Ok(old) => old,
+        Err(e) if cli.suggest_version => return Err(e.into()),
+        Err(_) => return Ok(false),
+    };
+
+    let finding = new.ok().and_then(|new| version::check(&old, &new, diff));
+    if let Some(finding) = &finding {
+        if !diff.is_empty() {
+            writeln!(out)?;
         }
+        writeln!(out, "Version check:")?;
+        writeln!(out, "  {finding}")?;
+    }
+
+    if cli.suggest_version {
+        let next = version::next_version(&old, version::required_bump(diff, &old));
+        writeln!(out)?;
+        writeln!(out, "Suggested version: {next}")?;
+    }
+
+    Ok(matches!(finding, Some(version::Finding::Error(_))))
+}
+
+/// The plugin named by the first argument, unless it names a spec file.
+fn plugin_command(args: &[OsString]) -> Result<Option<PathBuf>, String> {
+    let Some(name) = args.get(1).and_then(|a| a.to_str()) else {
+        return Ok(None);
+    };
+    let command = Cli::command().find_subcommand(name).is_some();
+    if command || !plugin::is_name(name) || Path::new(name).exists() {
+        return Ok(None);
     }
+    match plugin::find(name) {
+        Some(path) => Ok(Some(path)),
+        None => Err(format!(
+            "'{name}' is neither a spec file nor a plugin ('apidiff-{name}' not found on PATH)"
+        )),
+    }
+}
 
-    if diff.has_breaking() {
+fn run(
+    cli: DiffCli,
+    judging: &DiffArgs,
+    plugin: Option<PathBuf>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if plugin.is_none() && !cli.plugin_args.is_empty() {
+        return Err("arguments after '--' are only passed to plugins".into());
+    }
+    let options = judging.options()?;
+    let old_spec = loader::load_file(&cli.old)?;
+    let new_spec = loader::load_file(&cli.new)?;
+    let diff = apidiff::diff_specs_with(&old_spec, &new_spec, &options);
+
+    if let Some(path) = plugin {
+        let input = plugin::Input::new(&old_spec, &new_spec, &diff);
+        std::process::exit(plugin::run(&path, &cli.plugin_args, &input)?);
+    }
+
+    let suite = format!("{} -> {}", cli.old.display(), cli.new.display());
+    let specs = Some((&old_spec, &new_spec));
+    print!(
+        "{}",
+        report(cli.format, cli.template.as_deref(), &suite, &diff, specs)?
+    );
+
+    // Keep machine-readable reports intact.
+    let version_error = if cli.format.is_document() || cli.template.is_some() {
+        check_version(&cli, &old_spec, &new_spec, &diff, &mut io::stderr())?
+    } else {
+        check_version(&cli, &old_spec, &new_spec, &diff, &mut io::stdout())?
+    };
+
+    if diff.fails_on(cli.fail_on.into()) || version_error {
         std::process::exit(1);
     }
 
     Ok(())
 }
 
-fn main() {
-    let cli = Cli::parse();
+/// Render `diff` in `format`, or through `template`. Formats other than
+/// text, tree, Markdown and JSON need the specs the diff was made from.
+fn report(
+    format: Format,
+    template: Option<&Path>,
+    suite: &str,
+    diff: &Diff,
+    specs: Option<(&OpenAPI, &OpenAPI)>,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let needs_specs = |what: &str| {
+        specs.ok_or_else(|| format!("{what} needs the specs, which the report doesn't include"))
+    };
+    if let Some(path) = template {
+        let (old, new) = needs_specs("--template")?;
+        let source = std::fs::read_to_string(path)
+            .map_err(|e| format!("failed to read '{}': {e}", path.display()))?;
+        let name = path.display().to_string();
+        return Ok(render::template(&name, &source, diff, old, new)?);
+    }
+    let name = format!(
+        "--format {}",
+        format
+            .to_possible_value()
+            .expect("no skipped formats")
+            .get_name()
+    );
+    Ok(match format {
+        Format::Html => {
+            let (old, new) = needs_specs(&name)?;
+            render::html(diff, old, new)
+        }
+        Format::Junit => {
+            let (old, new) = needs_specs(&name)?;
+            render::junit(diff, suite, old, new)
+        }
+        Format::Migration => {
+            let (old, new) = needs_specs(&name)?;
+            render::migration(diff, old, new)
+        }
+        Format::Markdown => render::markdown(diff),
+        Format::Json => {
+            let input = match specs {
+                Some((old, new)) => plugin::Input::new(old, new, diff),
+                None => plugin::Input::without_specs(diff),
+            };
+            format!("{}\n", serde_json::to_string_pretty(&input)?)
+        }
+        _ if diff.is_empty() => "No changes detected.\n".into(),
+        Format::Text => render::text(diff),
+        Format::Tree => render::tree(&diff.tree()),
+    })
+}
+
+fn render_saved(cli: RenderCli) -> Result<(), Box<dyn std::error::Error>> {
+    let saved = Report::load(&cli.report)?;
+    let diff = match &cli.since {
+        Some(path) => {
+            let previous = Report::load(path)?.diff;
+            for change in saved.diff.resolved_since(&previous) {
+                eprintln!("Resolved: [{}] {change}", change.severity);
+            }
+            saved.diff.since(&previous)
+        }
+        None => saved.diff,
+    };
+    let specs = saved.old.as_ref().zip(saved.new.as_ref());
+    let suite = cli.report.display().to_string();
+    print!(
+        "{}",
+        report(cli.format, cli.template.as_deref(), &suite, &diff, specs)?
+    );
+    Ok(())
+}
 
-    if let Err(e) = run(cli) {
+fn changelog(cli: ChangelogCli) -> Result<(), Box<dyn std::error::Error>> {
+    let options = cli.diff.options()?;
+    let specs = cli
+        .specs
+        .iter()
+        .map(|path| loader::load_file(path))
+        .collect::<Result<Vec<_>, _>>()?;
+    let diffs: Vec<Diff> = specs
+        .windows(2)
+        .map(|pair| apidiff::diff_specs_with(&pair[0], &pair[1], &options))
+        .collect();
+    let releases: Vec<(&str, &Diff)> = specs[1..]
+        .iter()
+        .map(|spec| spec.info.version.as_str())
+        .zip(&diffs)
+        .collect();
+    print!("{}", render::changelog(&releases));
+    Ok(())
+}
+
+/// Run the built-in command, plugin or diff the arguments ask for.
+fn dispatch(mut args: Vec<OsString>) -> Result<(), Box<dyn std::error::Error>> {
+    let plugin = plugin_command(&args)?;
+    if plugin.is_some() {
+        args.remove(1);
+    }
+    let cli = Cli::parse_from(args);
+    match (cli.command, cli.diff) {
+        (Some(Command::Changelog(cli)), _) => changelog(cli),
+        (Some(Command::Render(cli)), _) => render_saved(cli),
+        (None, Some(diff)) => run(diff, &cli.judging, plugin),
+        (None, None) => unreachable!("clap requires the specs without a command"),
+    }
+}
+
+fn main() {
+    if let Err(e) = dispatch(std::env::args_os().collect()) {
         eprintln!("Error: {e}");
         std::process::exit(2);
     }
@@ -0,0 +1,218 @@
+//! External subcommands: `apidiff foo` runs `apidiff-foo` from `PATH`.
+//!
+//! A plugin receives its own arguments on the command line and one JSON
+//! document on stdin:
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "old": { "openapi": "3.0.3", "info": { ... }, "paths": { ... } },
+//!   "new": { "openapi": "3.0.3", "info": { ... }, "paths": { ... } },
+//!   "diff": {
+//!     "changes": [
+//!       {
+//!         "severity": "breaking",
+//!         "location": { "path": "/pets" },
+//!         "message": "endpoint removed",
+//!         "text": "/pets - endpoint removed",
+//!         "fingerprint": "5fad2cb78f3af91e",
+//!         "event": { "kind": "PathRemoved", "location": { "path": "/pets" }, ... }
+//!       }
+//!     ],
+//!     "usages": [
+//!       { "component": "Pet", "location": { ... }, "direction": "response" }
+//!     ]
+//!   }
+//! }
+//! ```
+//!
+//! `version` is [`PROTOCOL_VERSION`]; it changes only when existing fields
+//! change meaning or go away, so plugins should reject versions they don't
+//! know rather than guess. Fields may be added within a version. The
+//! severity is one of `breaking`, `warning`, `non-breaking`, `info` or
+//! `unstable`; the location is a [`Location`](crate::Location) with its
+//! variant as the key. `event` is the [`Event`](crate::diff::Event) the
+//! change was classified from, with its kind and fields, absent for
+//! findings of rules. `usages` is only filled with
+//! `--group-components`. Plugins always get `old` and `new`; `--format
+//! json` writes the same document, without them when re-rendering a report
+//! that didn't include the specs. Whatever the plugin prints is its report, and its
+//! exit code becomes apidiff's.
+
+use openapiv3::OpenAPI;
+use serde::Serialize;
+use std::ffi::OsStr;
+use std::fmt;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::change::Change;
+use crate::diff::{Diff, Usage};
+
+/// Version of the JSON document plugins receive on stdin.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Prefix of plugin executables: `apidiff foo` runs `apidiff-foo`.
+const PREFIX: &str = "apidiff-";
+
+/// The document sent to a plugin, and written by `--format json`.
+#[derive(Debug, Serialize)]
+pub struct Input<'a> {
+    version: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    old: Option<&'a OpenAPI>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    new: Option<&'a OpenAPI>,
+    diff: Report<'a>,
+}
+
+#[derive(Debug, Serialize)]
+struct Report<'a> {
+    changes: Vec<Entry<'a>>,
+    usages: &'a [Usage],
+}
+
+/// A change as plugins see it: its fields plus the rendered line and
+/// fingerprint, so plugins needn't reimplement either.
+#[derive(Debug, Serialize)]
+struct Entry<'a> {
+    #[serde(flatten)]
+    change: &'a Change,
+    text: String,
+    fingerprint: String,
+}
+
+impl<'a> Input<'a> {
+    /// The document for `diff` between `old` and `new`.
+    pub fn new(old: &'a OpenAPI, new: &'a OpenAPI, diff: &'a Diff) -> Self {
+        Input {
+            old: Some(old),
+            new: Some(new),
+            ..Input::without_specs(diff)
+        }
+    }
+
+    /// The document for a `diff` whose specs are not at hand, such as one
+    /// read back from a bare serialized [`Diff`]. It has no `old` or `new`.
+    pub fn without_specs(diff: &'a Diff) -> Self {
+        let changes = diff
+            .changes()
+            .iter()
+            .map(|change| Entry {
+                change,
+                text: change.to_string(),
+                fingerprint: change.fingerprint(),
+            })
+            .collect();
+        Input {
+            version: PROTOCOL_VERSION,
+            old: None,
+            new: None,
+            diff: Report {
+                changes,
+                usages: diff.usages(),
+            },
+        }
+    }
+}
+
+/// A plugin that could not be run.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum PluginError {
+    /// The executable could not be started or fed its input.
+    Io(PathBuf, io::Error),
+    /// The plugin was killed by a signal instead of exiting.
+    Killed(PathBuf),
+}
+
+impl fmt::Display for PluginError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PluginError::Io(path, e) => write!(f, "failed to run '{}': {e}", path.display()),
+            PluginError::Killed(path) => {
+                write!(f, "plugin '{}' was terminated by a signal", path.display())
+            }
+        }
+    }
+}
+
+impl std::error::Error for PluginError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PluginError::Io(_, e) => Some(e),
+            PluginError::Killed(_) => None,
+        }
+    }
+}
+
+/// Whether `arg` could name a plugin, as opposed to a spec file or an
+/// option.
+pub fn is_name(arg: &str) -> bool {
+    !arg.is_empty()
+        && !arg.starts_with('-')
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// The executable for plugin `name` on `PATH`, if any.
+pub fn find(name: &str) -> Option<PathBuf> {
+    find_in(name, &std::env::var_os("PATH")?)
+}
+
+/// The executable for plugin `name` in the directories listed in `path`,
+/// searched in order.
+pub fn find_in(name: &str, path: &OsStr) -> Option<PathBuf> {
+    let file = format!("{PREFIX}{name}{}", std::env::consts::EXE_SUFFIX);
+    std::env::split_paths(path)
+        .map(|dir| dir.join(&file))
+        .find(|candidate| is_executable(candidate))
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+/// Run the plugin at `path` with `args`, writing `input` to its stdin, and
+/// return its exit code. Its stdout and stderr are apidiff's.
+pub fn run(path: &Path, args: &[String], input: &Input) -> Result<i32, PluginError> {
+    let io_error = |e| PluginError::Io(path.to_path_buf(), e);
+    let mut child = Command::new(path)
+        .args(args)
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(io_error)?;
+
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let written = serde_json::to_writer(&mut stdin, input)
+        .map_err(io::Error::from)
+        .and_then(|()| stdin.flush());
+    drop(stdin);
+    // A plugin may exit without reading all of its input.
+    match written {
+        Err(e) if e.kind() != io::ErrorKind::BrokenPipe => {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(io_error(e));
+        }
+        _ => {}
+    }
+
+    let status = child.wait().map_err(io_error)?;
+    status
+        .code()
+        .ok_or_else(|| PluginError::Killed(path.to_path_buf()))
+}
+
+#[cfg(test)]
+mod tests;
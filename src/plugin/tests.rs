@@ -0,0 +1,139 @@
+use super::*;
+use crate::diff::diff_specs;
+use serde_json::json;
+use tempfile::TempDir;
+
+fn spec(param: &str) -> OpenAPI {
+    serde_yml::from_str(&format!(
+        r#"
+openapi: "3.0.3"
+info:
+  title: Test
+  version: "1.0.0"
+paths:
+  /pets:
+    get:
+      parameters:
+        - name: {param}
+          in: query
+          required: true
+          schema:
+            type: string
+      responses:
+        "200":
+          description: OK
+"#
+    ))
+    .unwrap()
+}
+
+#[test]
+fn input_carries_the_versioned_diff_and_both_specs() {
+    let (old, new) = (spec("limit"), spec("offset"));
+    let diff = diff_specs(&old, &new);
+    let input = serde_json::to_value(Input::new(&old, &new, &diff)).unwrap();
+
+    assert_eq!(input["version"], PROTOCOL_VERSION);
+    assert_eq!(
+        input["old"]["paths"]["/pets"]["get"]["parameters"][0]["name"],
+        "limit"
+    );
+    assert_eq!(
+        input["new"]["paths"]["/pets"]["get"]["parameters"][0]["name"],
+        "offset"
+    );
+    assert_eq!(
+        input["diff"]["changes"][0],
+        json!({
+            "severity": "breaking",
+            "location": {
+                "parameter": {
+                    "parent": { "operation": { "path": "/pets", "method": "GET" } },
+                    "name": "limit",
+                    "in": "query",
+                }
+            },
+            "message": "removed",
+            "text": "GET /pets - query parameter 'limit': removed",
+            "fingerprint": diff[0].fingerprint(),
+            "event": {
+                "kind": "ParameterRemoved",
+                "name": "limit",
+                "in": "query",
+                "required": true,
+                "location": {
+                    "parameter": {
+                        "parent": { "operation": { "path": "/pets", "method": "GET" } },
+                        "name": "limit",
+                        "in": "query",
+                    }
+                },
+                "direction": "request",
+                "deprecated": false,
+                "sunset": null,
+            },
+        })
+    );
+    assert_eq!(input["diff"]["usages"], json!([]));
+}
+
+#[test]
+fn plugin_names_exclude_options_and_paths() {
+    assert!(is_name("html-report"));
+    assert!(!is_name("--format"));
+    assert!(!is_name("old.yaml"));
+    assert!(!is_name("specs/old"));
+    assert!(!is_name(""));
+}
+
+#[cfg(unix)]
+fn install(dir: &Path, name: &str, script: &str) -> PathBuf {
+    use std::os::unix::fs::PermissionsExt;
+    let path = dir.join(name);
+    std::fs::write(&path, script).unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    path
+}
+
+#[cfg(unix)]
+#[test]
+fn plugins_are_found_in_path_order_and_must_be_executable() {
+    let (first, second) = (TempDir::new().unwrap(), TempDir::new().unwrap());
+    std::fs::write(first.path().join("apidiff-report"), "not executable").unwrap();
+    let expected = install(second.path(), "apidiff-report", "#!/bin/sh\n");
+    install(first.path(), "apidiff-lint", "#!/bin/sh\n");
+
+    let path = std::env::join_paths([first.path(), second.path()]).unwrap();
+    assert_eq!(find_in("report", &path), Some(expected));
+    assert_eq!(
+        find_in("lint", &path),
+        Some(first.path().join("apidiff-lint"))
+    );
+    assert_eq!(find_in("missing", &path), None);
+}
+
+#[cfg(unix)]
+#[test]
+fn plugins_read_the_input_and_their_exit_code_is_returned() {
+    let dir = TempDir::new().unwrap();
+    let received = dir.path().join("input.json");
+    let plugin = install(
+        dir.path(),
+        "apidiff-save",
+        "#!/bin/sh\ncat > \"$1\"\nexit 3\n",
+    );
+
+    let (old, new) = (spec("limit"), spec("offset"));
+    let diff = diff_specs(&old, &new);
+    let input = Input::new(&old, &new, &diff);
+    let args = [received.display().to_string()];
+    assert_eq!(run(&plugin, &args, &input).unwrap(), 3);
+
+    let sent: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&received).unwrap()).unwrap();
+    assert_eq!(sent, serde_json::to_value(&input).unwrap());
+
+    // Plugins needn't read their input.
+    let quiet = install(dir.path(), "apidiff-quiet", "#!/bin/sh\nexit 0\n");
+    assert_eq!(run(&quiet, &[], &input).unwrap(), 0);
+}
@@ -0,0 +1,154 @@
+//! Rendering diffs for people.
+
+use crate::change::{Change, Location};
+use crate::diff::{Diff, Node};
+
+mod changelog;
+mod html;
+mod junit;
+mod markdown;
+mod migration;
+mod node;
+mod template;
+
+pub use changelog::changelog;
+pub use html::html;
+pub use junit::junit;
+pub use markdown::markdown;
+pub use migration::migration;
+pub use template::template;
+
+/// Render changes grouped by severity, most severe first. Changes to grouped
+/// components are listed under the component with the operations using it.
+pub fn text(diff: &Diff) -> String {
+    let sections = [
+        ("Breaking changes", diff.breaking()),
+        ("Warnings", diff.warnings()),
+        ("Non-breaking changes", diff.non_breaking()),
+        ("Info", diff.info()),
+        ("Unstable changes", diff.unstable()),
+    ];
+    let mut out = String::new();
+    for (title, changes) in sections {
+        if changes.is_empty() {
+            continue;
+        }
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        out.push_str(&format!("{title} ({}):\n", changes.len()));
+        write_changes(&mut out, diff, &changes);
+    }
+    out
+}
+
+fn write_changes(out: &mut String, diff: &Diff, changes: &[&Change]) {
+    let mut components: Vec<&str> = Vec::new();
+    for c in changes {
+        match c.location.scope() {
+            Location::Component(name) => {
+                if !components.contains(&name.as_str()) {
+                    components.push(name);
+                }
+            }
+            _ => out.push_str(&format!("  {c}\n")),
+        }
+    }
+
+    for name in components {
+        let used_by: Vec<String> = diff
+            .affected_by(name)
+            .iter()
+            .map(|u| format!("{} ({})", u.location, u.direction))
+            .collect();
+        if used_by.is_empty() {
+            out.push_str(&format!("  component {name}:\n"));
+        } else {
+            out.push_str(&format!(
+                "  component {name} - used by {}:\n",
+                used_by.join(", ")
+            ));
+        }
+        for c in changes {
+            if matches!(c.location.scope(), Location::Component(n) if n == name) {
+                out.push_str(&format!("    {}\n", c.describe()));
+            }
+        }
+    }
+}
+
+/// Render a diff tree for the terminal. A chain of nodes without changes of
+/// their own is collapsed onto one line, e.g. `/pets GET responses '200'`.
+pub fn tree(root: &Node) -> String {
+    let mut out = String::new();
+    for change in &root.changes {
+        out.push_str(&format!("[{}] {}\n", change.severity, change.message));
+    }
+    for child in &root.children {
+        let (label, child) = collapse(child);
+        out.push_str(&node_line(&label, child));
+        write_children(&mut out, child, "");
+    }
+    out
+}
+
+fn node_line(label: &str, node: &Node) -> String {
+    match node.severity {
+        Some(severity) => format!("{label} [{severity}]\n"),
+        None => format!("{label}\n"),
+    }
+}
+
+fn write_children(out: &mut String, node: &Node, prefix: &str) {
+    let entries = node.changes.len() + node.children.len();
+    let connector = |i: usize| {
+        if i + 1 == entries {
+            ("└── ", "    ")
+        } else {
+            ("├── ", "│   ")
+        }
+    };
+    for (i, change) in node.changes.iter().enumerate() {
+        let (branch, _) = connector(i);
+        out.push_str(&format!(
+            "{prefix}{branch}[{}] {}\n",
+            change.severity, change.message
+        ));
+    }
+    for (i, child) in node.children.iter().enumerate() {
+        let (branch, indent) = connector(node.changes.len() + i);
+        let (label, child) = collapse(child);
+        out.push_str(&format!("{prefix}{branch}{}", node_line(&label, child)));
+        write_children(out, child, &format!("{prefix}{indent}"));
+    }
+}
+
+/// Follow single-child nodes without changes, joining their labels.
+fn collapse<'n, 'a>(node: &'n Node<'a>) -> (String, &'n Node<'a>) {
+    let mut label = node.label.clone();
+    let mut node = node;
+    while let ([child], []) = (node.children.as_slice(), node.changes.as_slice()) {
+        label = format!("{label} {}", child.label);
+        node = child;
+    }
+    (label, node)
+}
+
+/// Escape text for HTML and XML.
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests;
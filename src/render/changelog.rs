@@ -0,0 +1,102 @@
+//! Keep-a-Changelog Markdown across several releases.
+
+use crate::change::{Change, Location, Severity};
+use crate::diff::Diff;
+use crate::diff::EventKind::*;
+
+/// Sections of a release, in the order they are written.
+const SECTIONS: [&str; 5] = ["Added", "Changed", "Deprecated", "Removed", "Breaking"];
+
+/// The section a change is listed under, or `None` for documentation-only
+/// changes. Breaking changes get their own section; the others are sorted
+/// by the kind of event they come from, and findings of rules are listed
+/// as changed.
+fn section(change: &Change) -> Option<&'static str> {
+    match change.severity {
+        Severity::Info => return None,
+        Severity::Breaking => return Some("Breaking"),
+        _ => {}
+    }
+    let Some(event) = &change.event else {
+        return Some("Changed");
+    };
+    Some(match event.kind {
+        ServerAdded { .. }
+        | ServerVariableAdded { .. }
+        | ServerVariableValueAdded { .. }
+        | PathAdded
+        | OperationAdded
+        | CallbackAdded { .. }
+        | ParameterAdded { .. }
+        | RequestBodyAdded { .. }
+        | ResponseAdded { .. }
+        | HeaderAdded { .. }
+        | MediaTypeAdded { .. }
+        | PropertyAdded { .. }
+        | EnumValueAdded { .. }
+        | SecurityRequirementAdded { .. }
+        | ScopeAdded { .. } => "Added",
+        Deprecated { .. } => "Deprecated",
+        ServerRemoved { .. }
+        | ServerVariableRemoved { .. }
+        | ServerVariableValueRemoved { .. }
+        | BasePathRemoved { .. }
+        | PathRemoved
+        | OperationRemoved
+        | CallbackRemoved { .. }
+        | ParameterRemoved { .. }
+        | RequestBodyRemoved
+        | ResponseRemoved { .. }
+        | HeaderRemoved { .. }
+        | MediaTypeRemoved { .. }
+        | PropertyRemoved { .. }
+        | EnumValueRemoved { .. }
+        | SecurityRequirementRemoved { .. }
+        | ScopeRemoved { .. } => "Removed",
+        _ => "Changed",
+    })
+}
+
+fn entry(change: &Change) -> String {
+    let unstable = match change.severity {
+        Severity::Unstable => " (unstable)",
+        _ => "",
+    };
+    match change.location.scope() {
+        Location::Document => format!("- {}{unstable}\n", change.describe()),
+        scope => format!("- `{scope}`: {}{unstable}\n", change.describe()),
+    }
+}
+
+/// Render a changelog from `releases`, each a version and the diff from the
+/// release before it, oldest first. Releases are written newest first,
+/// with documentation-only changes left out.
+pub fn changelog(releases: &[(&str, &Diff)]) -> String {
+    let mut out = String::from("# Changelog\n\n");
+    out.push_str("All notable changes to this API are documented in this file.\n\n");
+    out.push_str(
+        "The format is based on [Keep a Changelog](https://keepachangelog.com/en/1.1.0/).\n",
+    );
+    for (version, diff) in releases.iter().rev() {
+        out.push_str(&format!("\n## [{version}]\n"));
+        let mut empty = true;
+        for title in SECTIONS {
+            let entries: Vec<String> = diff
+                .changes()
+                .iter()
+                .filter(|c| section(c) == Some(title))
+                .map(entry)
+                .collect();
+            if entries.is_empty() {
+                continue;
+            }
+            empty = false;
+            out.push_str(&format!("\n### {title}\n\n"));
+            out.push_str(&entries.concat());
+        }
+        if empty {
+            out.push_str("\nNo API changes.\n");
+        }
+    }
+    out
+}
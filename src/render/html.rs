@@ -0,0 +1,274 @@
+//! A self-contained HTML report.
+
+use openapiv3::OpenAPI;
+use serde_json::Value;
+
+use super::{escape, node};
+use crate::change::{Change, Location, Severity};
+use crate::diff::Diff;
+
+const SEVERITIES: [Severity; 5] = [
+    Severity::Breaking,
+    Severity::Warning,
+    Severity::NonBreaking,
+    Severity::Info,
+    Severity::Unstable,
+];
+
+const STYLE: &str = r#"
+body { font: 14px/1.5 system-ui, sans-serif; margin: 0 auto; max-width: 1200px; padding: 1em 2em; color: #1f2328; }
+h1 { margin-bottom: 0; }
+.versions { color: #59636e; margin-top: 0; }
+.dashboard { display: flex; gap: 1em; flex-wrap: wrap; margin: 1em 0; }
+.card { border: 1px solid #d1d9e0; border-radius: 6px; padding: .5em 1em; min-width: 8em; }
+.card .count { font-size: 2em; font-weight: 600; }
+#filters { display: flex; gap: 1.5em; flex-wrap: wrap; align-items: center; padding: .5em 0; border-bottom: 1px solid #d1d9e0; margin-bottom: 1em; }
+.endpoint { border: 1px solid #d1d9e0; border-radius: 6px; margin: .5em 0; }
+.endpoint > summary { cursor: pointer; padding: .5em 1em; font-family: ui-monospace, monospace; font-weight: 600; }
+.endpoint > summary .tags { font-family: system-ui, sans-serif; font-weight: normal; color: #59636e; }
+.usages { margin: 0 1em; color: #59636e; }
+.change { border-top: 1px solid #eff2f5; padding: .4em 1em; }
+.change summary { cursor: pointer; color: #59636e; }
+.badge { display: inline-block; border-radius: 1em; padding: 0 .6em; font-size: .85em; color: #fff; margin-right: .5em; }
+.breaking { background: #cf222e; }
+.warning { background: #bc4c00; }
+.non-breaking { background: #1a7f37; }
+.info { background: #0969da; }
+.unstable { background: #6e7781; }
+.card.breaking, .card.warning, .card.non-breaking, .card.info, .card.unstable { color: #fff; }
+.sides { display: grid; grid-template-columns: 1fr 1fr; gap: .5em; }
+.sides h4 { margin: .3em 0; }
+pre { background: #f6f8fa; border-radius: 6px; padding: .5em; margin: 0; overflow: auto; max-height: 30em; font-size: 12px; }
+.absent { color: #59636e; font-style: italic; }
+"#;
+
+const SCRIPT: &str = r#"
+const boxes = [...document.querySelectorAll('#filters input[name=severity]')];
+const tag = document.getElementById('tag');
+const path = document.getElementById('path');
+function apply() {
+  const severities = new Set(boxes.filter(b => b.checked).map(b => b.value));
+  const wanted = tag.value;
+  const needle = path.value.toLowerCase();
+  for (const group of document.querySelectorAll('.endpoint')) {
+    const matches = (!wanted || group.dataset.tags.split('|').includes(wanted))
+      && group.dataset.path.toLowerCase().includes(needle);
+    let shown = 0;
+    for (const change of group.querySelectorAll('.change')) {
+      change.hidden = !(matches && severities.has(change.dataset.severity));
+      if (!change.hidden) shown++;
+    }
+    group.hidden = shown === 0;
+  }
+}
+for (const input of [...boxes, tag, path]) input.addEventListener('input', apply);
+function expand(open) {
+  for (const group of document.querySelectorAll('.endpoint')) group.open = open;
+}
+document.getElementById('expand').addEventListener('click', () => expand(true));
+document.getElementById('collapse').addEventListener('click', () => expand(false));
+"#;
+
+/// Changes sharing a scope, such as an operation or a component.
+struct Group<'a> {
+    scope: &'a Location,
+    changes: Vec<&'a Change>,
+    tags: Vec<String>,
+}
+
+/// Render a single HTML file with no external assets: a summary of the
+/// changes by severity, filters by severity, tag and path, and the changes
+/// grouped by endpoint, each with the old and new spec snippets at its
+/// location.
+pub fn html(diff: &Diff, old: &OpenAPI, new: &OpenAPI) -> String {
+    let old_json = serde_json::to_value(old).unwrap_or_default();
+    let new_json = serde_json::to_value(new).unwrap_or_default();
+    let groups = groups(diff, &old_json, &new_json);
+
+    let mut out = String::new();
+    let title = format!("API changes: {}", new.info.title);
+    out.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+    out.push_str("<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n");
+    out.push_str(&format!("<title>{}</title>\n", escape(&title)));
+    out.push_str(&format!("<style>{STYLE}</style>\n</head>\n<body>\n"));
+    out.push_str(&format!("<h1>{}</h1>\n", escape(&title)));
+    out.push_str(&format!(
+        "<p class=\"versions\">{} &rarr; {}</p>\n",
+        escape(&old.info.version),
+        escape(&new.info.version)
+    ));
+
+    write_dashboard(&mut out, diff, groups.len());
+    if diff.is_empty() {
+        out.push_str("<p>No changes detected.</p>\n</body>\n</html>\n");
+        return out;
+    }
+    write_filters(&mut out, &groups);
+    for group in &groups {
+        write_group(&mut out, diff, group, &old_json, &new_json);
+    }
+    out.push_str(&format!("<script>{SCRIPT}</script>\n</body>\n</html>\n"));
+    out
+}
+
+/// Changes grouped by scope, in the order the scopes first appear.
+fn groups<'a>(diff: &'a Diff, old: &Value, new: &Value) -> Vec<Group<'a>> {
+    let mut groups: Vec<Group> = Vec::new();
+    for change in diff.changes() {
+        let scope = change.location.scope();
+        match groups.iter_mut().find(|g| g.scope == scope) {
+            Some(group) => group.changes.push(change),
+            None => groups.push(Group {
+                scope,
+                changes: vec![change],
+                tags: Vec::new(),
+            }),
+        }
+    }
+    for group in &mut groups {
+        let mut operations = vec![group.scope.clone()];
+        if let Location::Component(name) = group.scope {
+            operations.extend(diff.affected_by(name).iter().map(|u| u.location.clone()));
+        }
+        for operation in &operations {
+            for tag in [old, new].iter().flat_map(|spec| tags(operation, spec)) {
+                if !group.tags.contains(&tag) {
+                    group.tags.push(tag);
+                }
+            }
+        }
+    }
+    groups
+}
+
+/// Tags of the operations at or declaring `scope`.
+fn tags(scope: &Location, spec: &Value) -> Vec<String> {
+    let operations = match scope {
+        Location::Callback { parent, .. } => return tags(parent, spec),
+        Location::Path(_) | Location::Webhook(_) => match node::find(scope, spec) {
+            Some(Value::Object(item)) => item.into_values().collect(),
+            _ => Vec::new(),
+        },
+        _ => node::find(scope, spec).into_iter().collect(),
+    };
+    operations
+        .iter()
+        .filter_map(|op| op.get("tags")?.as_array())
+        .flatten()
+        .filter_map(|tag| tag.as_str().map(str::to_string))
+        .collect()
+}
+
+fn label(scope: &Location) -> String {
+    match scope {
+        Location::Document => "Document".into(),
+        scope => scope.to_string(),
+    }
+}
+
+fn write_dashboard(out: &mut String, diff: &Diff, groups: usize) {
+    out.push_str("<section class=\"dashboard\">\n");
+    out.push_str(&format!(
+        "<div class=\"card\"><div class=\"count\">{}</div>changes</div>\n",
+        diff.len()
+    ));
+    out.push_str(&format!(
+        "<div class=\"card\"><div class=\"count\">{groups}</div>endpoints and components</div>\n"
+    ));
+    for severity in SEVERITIES {
+        let count = diff
+            .changes()
+            .iter()
+            .filter(|c| c.severity == severity)
+            .count();
+        out.push_str(&format!(
+            "<div class=\"card {severity}\"><div class=\"count\">{count}</div>{severity}</div>\n"
+        ));
+    }
+    out.push_str("</section>\n");
+}
+
+fn write_filters(out: &mut String, groups: &[Group]) {
+    out.push_str("<section id=\"filters\">\n<div>Severity:");
+    for severity in SEVERITIES {
+        out.push_str(&format!(" <label><input type=\"checkbox\" name=\"severity\" value=\"{severity}\" checked> {severity}</label>"
+        ));
+    }
+    out.push_str("</div>\n<label>Tag: <select id=\"tag\"><option value=\"\">all</option>");
+    let mut tags: Vec<&String> = groups.iter().flat_map(|g| &g.tags).collect();
+    tags.sort();
+    tags.dedup();
+    for tag in tags {
+        let tag = escape(tag);
+        out.push_str(&format!("<option value=\"{tag}\">{tag}</option>"));
+    }
+    out.push_str("</select></label>\n");
+    out.push_str(
+        "<label>Path: <input type=\"search\" id=\"path\" placeholder=\"/pets\"></label>\n",
+    );
+    out.push_str("<div><button id=\"expand\">Expand all</button> <button id=\"collapse\">Collapse all</button></div>\n");
+    out.push_str("</section>\n");
+}
+
+fn write_group(out: &mut String, diff: &Diff, group: &Group, old: &Value, new: &Value) {
+    let label = label(group.scope);
+    out.push_str(&format!(
+        "<details class=\"endpoint\" open data-path=\"{}\" data-tags=\"{}\">\n",
+        escape(&label),
+        escape(&group.tags.join("|"))
+    ));
+    out.push_str(&format!("<summary>{}", escape(&label)));
+    for severity in SEVERITIES {
+        let count = group
+            .changes
+            .iter()
+            .filter(|c| c.severity == severity)
+            .count();
+        if count > 0 {
+            out.push_str(&format!(" <span class=\"badge {severity}\">{count}</span>"));
+        }
+    }
+    if !group.tags.is_empty() {
+        out.push_str(&format!(
+            " <span class=\"tags\">{}</span>",
+            escape(&group.tags.join(", "))
+        ));
+    }
+    out.push_str("</summary>\n");
+
+    if let Location::Component(name) = group.scope {
+        let used_by: Vec<String> = diff
+            .affected_by(name)
+            .iter()
+            .map(|u| format!("{} ({})", u.location, u.direction))
+            .collect();
+        if !used_by.is_empty() {
+            out.push_str(&format!(
+                "<p class=\"usages\">Used by {}</p>\n",
+                escape(&used_by.join(", "))
+            ));
+        }
+    }
+
+    for change in &group.changes {
+        out.push_str(&format!(
+            "<div class=\"change\" data-severity=\"{0}\"><span class=\"badge {0}\">{0}</span>{1}\n",
+            change.severity,
+            escape(&change.describe())
+        ));
+        out.push_str("<details><summary>Old / new</summary><div class=\"sides\">\n");
+        for (side, spec) in [("Old", old), ("New", new)] {
+            out.push_str(&format!("<div><h4>{side}</h4>"));
+            match node::find(&change.location, spec) {
+                Some(node) => {
+                    let json = serde_json::to_string_pretty(&node).unwrap_or_default();
+                    out.push_str(&format!("<pre>{}</pre>", escape(&json)));
+                }
+                None => out.push_str("<p class=\"absent\">Not present</p>"),
+            }
+            out.push_str("</div>\n");
+        }
+        out.push_str("</div></details>\n</div>\n");
+    }
+    out.push_str("</details>\n");
+}
@@ -0,0 +1,136 @@
+//! JUnit XML, for CI systems that only render test reports.
+
+use openapiv3::OpenAPI;
+
+use super::escape;
+use crate::change::{Change, Location, Severity};
+use crate::diff::Diff;
+
+/// A test case: an operation, or a scope outside any operation such as the
+/// document or a schema component.
+struct Case<'a> {
+    scope: Location,
+    changes: Vec<&'a Change>,
+}
+
+impl Case<'_> {
+    fn failures(&self) -> Vec<&Change> {
+        self.changes
+            .iter()
+            .copied()
+            .filter(|c| c.severity == Severity::Breaking)
+            .collect()
+    }
+}
+
+/// Render the diff as a JUnit test suite named `suite`. Every operation in
+/// either spec is a test case; breaking changes fail it and the other
+/// changes are listed in its `system-out`. Changes outside operations,
+/// such as to the document or a grouped component, get test cases of their
+/// own.
+pub fn junit(diff: &Diff, suite: &str, old: &OpenAPI, new: &OpenAPI) -> String {
+    let cases = cases(diff, old, new);
+    let failures = cases.iter().filter(|c| !c.failures().is_empty()).count();
+    let counts = format!(
+        "tests=\"{}\" failures=\"{failures}\" errors=\"0\"",
+        cases.len()
+    );
+    let suite = escape(suite);
+
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str(&format!("<testsuites name=\"apidiff\" {counts}>\n"));
+    out.push_str(&format!(
+        "  <testsuite name=\"{suite}\" {counts} skipped=\"0\">\n"
+    ));
+    for case in &cases {
+        let name = escape(&case.scope.to_string());
+        let open = format!("    <testcase name=\"{name}\" classname=\"{suite}\"");
+        if case.changes.is_empty() {
+            out.push_str(&format!("{open}/>\n"));
+            continue;
+        }
+        out.push_str(&format!("{open}>\n"));
+
+        let failures = case.failures();
+        let message = match failures.as_slice() {
+            [] => None,
+            [only] => Some(only.describe()),
+            all => Some(format!("{} breaking changes", all.len())),
+        };
+        if let Some(message) = message {
+            let details: Vec<String> = failures.iter().map(|c| escape(&c.describe())).collect();
+            out.push_str(&format!(
+                "      <failure message=\"{}\" type=\"breaking\">{}</failure>\n",
+                escape(&message),
+                details.join("\n")
+            ));
+        }
+
+        let others: Vec<String> = case
+            .changes
+            .iter()
+            .filter(|c| c.severity != Severity::Breaking)
+            .map(|c| escape(&format!("[{}] {}", c.severity, c.describe())))
+            .collect();
+        if !others.is_empty() {
+            out.push_str(&format!(
+                "      <system-out>{}</system-out>\n",
+                others.join("\n")
+            ));
+        }
+        out.push_str("    </testcase>\n");
+    }
+    out.push_str("  </testsuite>\n</testsuites>\n");
+    out
+}
+
+/// The operations of both specs, new ones first, with the changes to each.
+fn cases<'a>(diff: &'a Diff, old: &OpenAPI, new: &OpenAPI) -> Vec<Case<'a>> {
+    let mut cases: Vec<Case> = Vec::new();
+    for spec in [new, old] {
+        for (path, item) in spec.paths.iter() {
+            let Some(item) = item.as_item() else {
+                continue;
+            };
+            for (method, _) in item.iter() {
+                let scope = Location::Operation {
+                    path: path.clone(),
+                    method: method.to_uppercase(),
+                };
+                if !cases.iter().any(|c| c.scope == scope) {
+                    cases.push(Case {
+                        scope,
+                        changes: Vec::new(),
+                    });
+                }
+            }
+        }
+    }
+
+    for change in diff.changes() {
+        let scope = match change.location.scope() {
+            Location::Callback { parent, .. } => parent.scope(),
+            scope => scope,
+        };
+        // Changes to a whole path item, such as its removal, concern each
+        // of its operations.
+        let mut targets: Vec<&mut Case> = cases
+            .iter_mut()
+            .filter(|c| match (scope, &c.scope) {
+                (Location::Path(path), Location::Operation { path: p, .. }) => path == p,
+                _ => c.scope == *scope,
+            })
+            .collect();
+        if targets.is_empty() {
+            cases.push(Case {
+                scope: scope.clone(),
+                changes: vec![change],
+            });
+        } else {
+            for case in &mut targets {
+                case.changes.push(change);
+            }
+        }
+    }
+    cases
+}
@@ -0,0 +1,34 @@
+//! Markdown, for pull request comments and wikis.
+
+use crate::change::Location;
+use crate::diff::Diff;
+
+/// Render changes as Markdown lists grouped by severity, most severe first,
+/// each change led by its endpoint or component.
+pub fn markdown(diff: &Diff) -> String {
+    let mut out = String::from("# API changes\n");
+    if diff.is_empty() {
+        out.push_str("\nNo changes detected.\n");
+        return out;
+    }
+    let sections = [
+        ("Breaking changes", diff.breaking()),
+        ("Warnings", diff.warnings()),
+        ("Non-breaking changes", diff.non_breaking()),
+        ("Info", diff.info()),
+        ("Unstable changes", diff.unstable()),
+    ];
+    for (title, changes) in sections {
+        if changes.is_empty() {
+            continue;
+        }
+        out.push_str(&format!("\n## {title} ({})\n\n", changes.len()));
+        for c in changes {
+            match c.location.scope() {
+                Location::Document => out.push_str(&format!("- {}\n", c.describe())),
+                scope => out.push_str(&format!("- `{scope}`: {}\n", c.describe())),
+            }
+        }
+    }
+    out
+}
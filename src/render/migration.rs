@@ -0,0 +1,246 @@
+//! Migration guides: what integrators must change for a breaking release.
+
+use openapiv3::OpenAPI;
+use serde_json::Value;
+
+use super::node;
+use crate::change::Location;
+use crate::diff::{Diff, Direction, Event, EventKind};
+
+/// Render a Markdown guide with an instruction for every breaking change,
+/// grouped by endpoint. Instructions include examples from the new spec
+/// where it has them; changes no instruction fits are listed for review.
+pub fn migration(diff: &Diff, old: &OpenAPI, new: &OpenAPI) -> String {
+    let new_json = serde_json::to_value(new).unwrap_or_default();
+    let mut out = format!(
+        "# Migrating from {} {} to {}\n",
+        old.info.title, old.info.version, new.info.version
+    );
+
+    let breaking = diff.breaking();
+    if breaking.is_empty() {
+        out.push_str("\nNo breaking changes: existing clients need no changes.\n");
+        return out;
+    }
+    let mut scopes: Vec<&Location> = Vec::new();
+    for change in &breaking {
+        if !scopes.contains(&change.location.scope()) {
+            scopes.push(change.location.scope());
+        }
+    }
+    for scope in scopes {
+        match scope {
+            Location::Document => out.push_str("\n## All endpoints\n\n"),
+            scope => out.push_str(&format!("\n## {scope}\n\n")),
+        }
+        for change in breaking.iter().filter(|c| c.location.scope() == scope) {
+            let step = match &change.event {
+                Some(event) => instruction(event, &new_json),
+                None => None,
+            };
+            let step = step.unwrap_or_else(|| format!("Review: {}.", change.describe()));
+            out.push_str(&format!("- {step}\n"));
+        }
+    }
+    out
+}
+
+/// What a client has to do about `event`, if there is a standard answer.
+fn instruction(event: &Event, new: &Value) -> Option<String> {
+    let loc = &event.location;
+    let response = direction(event) == Direction::Response;
+    let at = place(loc);
+    let step = match &event.kind {
+        EventKind::PathRemoved | EventKind::OperationRemoved => {
+            format!("Stop calling `{}`: it was removed.", loc.scope())
+        }
+        EventKind::ServerRemoved { url } => format!("Stop using the server `{url}`."),
+        EventKind::BasePathChanged { from, to } => {
+            format!("Call the API under `{to}` instead of `{from}`.")
+        }
+        EventKind::BasePathRemoved { path } => {
+            format!("Stop calling the API under `{path}`.")
+        }
+        EventKind::CallbackRemoved { name } => {
+            format!("Stop relying on the `{name}` callback: it is no longer sent.")
+        }
+        EventKind::ParameterRemoved { name, r#in, .. } => {
+            format!("Stop sending the {in} `{name}`.", in = noun(r#in))
+        }
+        EventKind::ParameterAdded {
+            name,
+            r#in,
+            required: true,
+        } => format!(
+            "Send the new required {in} `{name}`.{}",
+            example(new, loc),
+            in = noun(r#in),
+        ),
+        EventKind::ParameterAdded { name, r#in, .. } => format!(
+            "Consider sending the new optional {in} `{name}`.{}",
+            example(new, loc),
+            in = noun(r#in),
+        ),
+        EventKind::RequestBodyRemoved => "Stop sending a request body.".into(),
+        EventKind::RequestBodyAdded { required: true } => {
+            format!("Send the new required request body.{}", example(new, loc))
+        }
+        EventKind::RequestBodyAdded { .. } => format!(
+            "Consider sending the new optional request body.{}",
+            example(new, loc)
+        ),
+        EventKind::ResponseRemoved { code } => {
+            format!("Stop relying on the `{code}` response: it is no longer returned.")
+        }
+        EventKind::ResponseAdded { code } => format!("Handle the new `{code}` response."),
+        EventKind::PrimarySuccessChanged { from, to } => {
+            format!("Expect status `{to}` instead of `{from}` on success.")
+        }
+        EventKind::HeaderRemoved { name } => {
+            format!("Stop relying on the response header `{name}`.")
+        }
+        EventKind::MediaTypeRemoved { media, .. } if response => {
+            format!("Stop expecting `{media}` in {at}.")
+        }
+        EventKind::MediaTypeRemoved { media, .. } => {
+            format!("Stop sending `{media}` in {at}.")
+        }
+        EventKind::MediaTypeReplaced { media, by } if response => {
+            format!("Accept `{by}` instead of `{media}` in {at}.")
+        }
+        EventKind::MediaTypeReplaced { media, by } => {
+            format!("Send `{by}` instead of `{media}` in {at}.")
+        }
+        EventKind::TypeChanged { to, .. } if response => {
+            format!("Expect {at} to be {}.", article(to))
+        }
+        EventKind::TypeChanged { to, .. } => {
+            format!("Send {at} as {}.{}", article(to), example(new, loc))
+        }
+        EventKind::PropertyRemoved { .. } if response => {
+            format!("Stop reading {at}: it is no longer returned.")
+        }
+        EventKind::PropertyRemoved { .. } => format!("Stop sending {at}."),
+        EventKind::PropertyAdded { .. } if response => format!("Handle the new property {at}."),
+        EventKind::PropertyAdded { required: true, .. } => {
+            format!("Send the new required property {at}.{}", example(new, loc))
+        }
+        EventKind::PropertyAdded { .. } => format!(
+            "Consider sending the new optional property {at}.{}",
+            example(new, loc)
+        ),
+        EventKind::PropertyBecameRequired { .. } | EventKind::BecameRequired => {
+            format!("Always send {at}.{}", example(new, loc))
+        }
+        EventKind::PropertyBecameOptional { .. } | EventKind::BecameOptional => {
+            format!("Handle {at} being absent.")
+        }
+        EventKind::EnumValueRemoved { value } if response => {
+            format!("Stop expecting the value `{value}` in {at}.")
+        }
+        EventKind::EnumValueRemoved { value } => {
+            format!("Stop sending the value `{value}` in {at}.")
+        }
+        EventKind::EnumValueAdded { value } => {
+            format!("Handle the new enum value `{value}` in {at}.")
+        }
+        EventKind::SecurityRequirementAdded { requirement, .. } => {
+            format!("Authenticate with `{requirement}`.")
+        }
+        EventKind::ScopeAdded { scope } => format!("Request the `{scope}` scope."),
+        EventKind::StabilityChanged { to, .. } => {
+            format!("Expect changes without notice: the operation is now `{to}`.")
+        }
+        _ => return None,
+    };
+    Some(step)
+}
+
+/// Which way the data the event is about flows. Events without a direction
+/// are about requests unless they sit under a response.
+fn direction(event: &Event) -> Direction {
+    event.direction.unwrap_or_else(|| {
+        let mut loc = Some(&event.location);
+        while let Some(l) = loc {
+            if matches!(l, Location::Response { .. }) {
+                return Direction::Response;
+            }
+            loc = l.parent();
+        }
+        Direction::Request
+    })
+}
+
+/// The element at `loc`, relative to its endpoint: `the request body`,
+/// `` `owner.name` in the `200` response ``, `` the query parameter `limit` ``.
+fn place(loc: &Location) -> String {
+    match loc {
+        Location::Schema { parent, path } => {
+            let path = path.trim_start_matches("[]").trim_start_matches('.');
+            match path {
+                "" => place(parent),
+                path => format!("`{path}` in {}", place(parent)),
+            }
+        }
+        Location::MediaType { parent, .. } => place(parent),
+        Location::RequestBody { .. } => "the request body".into(),
+        Location::Response { code, .. } => format!("the `{code}` response"),
+        Location::Header { name, .. } => format!("the header `{name}`"),
+        Location::Parameter { name, r#in, .. } => format!("the {} `{name}`", noun(r#in)),
+        Location::Component(name) => format!("the `{name}` schema"),
+        _ => "the operation".into(),
+    }
+}
+
+fn noun(r#in: &str) -> String {
+    match r#in {
+        "header" => "header".into(),
+        "cookie" => "cookie".into(),
+        other => format!("{other} parameter"),
+    }
+}
+
+fn article(word: &str) -> String {
+    match word.starts_with(['a', 'e', 'i', 'o', 'u']) {
+        true => format!("an {word}"),
+        false => format!("a {word}"),
+    }
+}
+
+/// ``For example: `value`.`` from the example, default or first enum value
+/// the new spec gives for `loc`, or nothing.
+fn example(new: &Value, loc: &Location) -> String {
+    let Some(node) = node::find(loc, new) else {
+        return String::new();
+    };
+    let schema = node.get("schema").unwrap_or(&node);
+    let first_example = |n: &Value| {
+        n.get("examples")?
+            .as_object()?
+            .values()
+            .find_map(|e| e.get("value").cloned())
+    };
+    let value = node
+        .get("example")
+        .cloned()
+        .or_else(|| first_example(&node))
+        .or_else(|| {
+            node.get("content")?
+                .as_object()?
+                .values()
+                .find_map(|media| {
+                    media
+                        .get("example")
+                        .cloned()
+                        .or_else(|| first_example(media))
+                })
+        })
+        .or_else(|| schema.get("example").cloned())
+        .or_else(|| schema.get("default").cloned())
+        .or_else(|| schema.get("enum")?.get(0).cloned());
+    match value {
+        Some(Value::String(text)) => format!(" For example: `{text}`."),
+        Some(value) => format!(" For example: `{value}`."),
+        None => String::new(),
+    }
+}
@@ -0,0 +1,158 @@
+//! Finding the spec node a change is located at, for renderers that show
+//! or quote it.
+
+use serde_json::Value;
+
+use crate::change::{Location, schema_steps};
+use crate::diff::WEBHOOKS;
+
+/// The node at `loc` in `spec`, an OpenAPI document as JSON, following
+/// `$ref`s on the way. Documents resolve to their `info` and `servers`,
+/// security schemes to the requirements that name them.
+pub(super) fn find(loc: &Location, spec: &Value) -> Option<Value> {
+    match loc {
+        Location::Document => {
+            let mut doc = serde_json::Map::new();
+            for key in ["info", "servers"] {
+                if let Some(value) = spec.get(key) {
+                    doc.insert(key.into(), value.clone());
+                }
+            }
+            Some(Value::Object(doc))
+        }
+        Location::Security { parent, scheme } => {
+            let operation = node(parent, spec)?;
+            let requirements = operation
+                .get("security")
+                .or_else(|| spec.get("security"))?
+                .as_array()?
+                .iter()
+                .filter(|r| r.get(scheme).is_some())
+                .cloned()
+                .collect::<Vec<_>>();
+            (!requirements.is_empty()).then_some(Value::Array(requirements))
+        }
+        _ => node(loc, spec).cloned(),
+    }
+}
+
+fn node<'a>(loc: &Location, spec: &'a Value) -> Option<&'a Value> {
+    let node = match loc {
+        Location::Document => spec,
+        Location::Path(path) => spec.get("paths")?.get(path)?,
+        Location::Operation { path, method } => {
+            node(&Location::Path(path.clone()), spec)?.get(method.to_lowercase())?
+        }
+        Location::Callback {
+            parent,
+            name,
+            inner,
+        } => {
+            let callback = node(parent, spec)?.get("callbacks")?.get(name)?;
+            within(inner, spec, resolve(spec, callback)?)?
+        }
+        Location::Webhook(inner) => WEBHOOKS
+            .iter()
+            .rev()
+            .find_map(|key| within(inner, spec, spec.get(key)?))?,
+        Location::Component(name) => spec.get("components")?.get("schemas")?.get(name)?,
+        Location::Parameter { parent, name, r#in } => {
+            let item = path_item(parent).and_then(|item| node(&item, spec));
+            [node(parent, spec), item]
+                .into_iter()
+                .flatten()
+                .filter_map(|node| node.get("parameters")?.as_array())
+                .flatten()
+                .filter_map(|p| resolve(spec, p))
+                .find(|p| {
+                    p.get("name") == Some(&name[..].into()) && p.get("in") == Some(&r#in[..].into())
+                })?
+        }
+        Location::RequestBody { parent } => node(parent, spec)?.get("requestBody")?,
+        Location::Response { parent, code } => node(parent, spec)?.get("responses")?.get(code)?,
+        Location::MediaType {
+            parent,
+            media,
+            new_media,
+        } => {
+            let content = node(parent, spec)?.get("content")?;
+            content
+                .get(media)
+                .or_else(|| content.get(new_media.as_deref()?))?
+        }
+        Location::Header { parent, name } => node(parent, spec)?.get("headers")?.get(name)?,
+        Location::Schema { parent, path } => {
+            let holder = node(parent, spec)?;
+            let root = match parent.as_ref() {
+                Location::Component(_) => holder,
+                _ => holder.get("schema")?,
+            };
+            schema_steps(path).try_fold(root, |schema, step| schema_step(spec, schema, step))?
+        }
+        Location::Server { parent, url } => node(parent, spec)?
+            .get("servers")?
+            .as_array()?
+            .iter()
+            .find(|s| s.get("url") == Some(&url[..].into()))?,
+        _ => return None,
+    };
+    resolve(spec, node)
+}
+
+/// Resolve a path or operation location relative to a map of path items,
+/// as found under a callback or a webhooks map.
+fn within<'a>(loc: &Location, spec: &'a Value, items: &'a Value) -> Option<&'a Value> {
+    match loc {
+        Location::Path(key) => resolve(spec, items.get(key)?),
+        Location::Operation { path, method } => resolve(
+            spec,
+            resolve(spec, items.get(path)?)?.get(method.to_lowercase())?,
+        ),
+        _ => None,
+    }
+}
+
+/// The path item of the operation at `loc`, whose parameters the operation
+/// inherits.
+fn path_item(loc: &Location) -> Option<Location> {
+    match loc {
+        Location::Operation { path, .. } => Some(Location::Path(path.clone())),
+        Location::Callback {
+            parent,
+            name,
+            inner,
+        } => Some(Location::Callback {
+            parent: parent.clone(),
+            name: name.clone(),
+            inner: Box::new(path_item(inner)?),
+        }),
+        Location::Webhook(inner) => Some(Location::Webhook(Box::new(path_item(inner)?))),
+        _ => None,
+    }
+}
+
+/// Follow a local `$ref` (`#/components/...`), leaving other nodes as they
+/// are.
+fn resolve<'a>(spec: &'a Value, node: &'a Value) -> Option<&'a Value> {
+    match node.get("$ref").and_then(Value::as_str) {
+        Some(reference) => resolve(spec, spec.pointer(reference.strip_prefix('#')?)?),
+        None => Some(node),
+    }
+}
+
+/// Step into a property (or `[]` for array items) of `schema`, looking
+/// through `allOf`, `oneOf` and `anyOf`.
+fn schema_step<'a>(spec: &'a Value, schema: &'a Value, step: &str) -> Option<&'a Value> {
+    let schema = resolve(spec, schema)?;
+    let direct = match step {
+        "[]" => schema.get("items"),
+        name => schema.get("properties").and_then(|p| p.get(name)),
+    };
+    direct.and_then(|s| resolve(spec, s)).or_else(|| {
+        ["allOf", "oneOf", "anyOf"]
+            .iter()
+            .filter_map(|key| schema.get(key)?.as_array())
+            .flatten()
+            .find_map(|member| schema_step(spec, member, step))
+    })
+}
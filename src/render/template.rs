@@ -0,0 +1,165 @@
+//! User-supplied output formats, rendered with
+//! [minijinja](https://docs.rs/minijinja).
+
+use minijinja::Environment;
+use openapiv3::OpenAPI;
+use serde::Serialize;
+
+use crate::change::{Change, Location, Severity};
+use crate::diff::Diff;
+
+/// Everything a template can use.
+#[derive(Serialize)]
+struct Context<'a> {
+    old: Info<'a>,
+    new: Info<'a>,
+    changes: Vec<Entry<'a>>,
+    counts: Counts,
+    has_breaking: bool,
+    by_severity: Vec<SeverityGroup<'a>>,
+    by_scope: Vec<ScopeGroup<'a>>,
+}
+
+#[derive(Serialize)]
+struct Info<'a> {
+    title: &'a str,
+    version: &'a str,
+    description: Option<&'a str>,
+}
+
+/// A change with the strings the built-in formats print.
+#[derive(Clone, Serialize)]
+struct Entry<'a> {
+    #[serde(flatten)]
+    change: &'a Change,
+    scope: String,
+    detail: String,
+    description: String,
+    text: String,
+    fingerprint: String,
+}
+
+#[derive(Serialize)]
+struct Counts {
+    total: usize,
+    breaking: usize,
+    warning: usize,
+    non_breaking: usize,
+    info: usize,
+    unstable: usize,
+}
+
+#[derive(Serialize)]
+struct SeverityGroup<'a> {
+    severity: Severity,
+    title: &'static str,
+    changes: Vec<Entry<'a>>,
+}
+
+#[derive(Serialize)]
+struct ScopeGroup<'a> {
+    scope: String,
+    changes: Vec<Entry<'a>>,
+}
+
+const SECTIONS: [(Severity, &str); 5] = [
+    (Severity::Breaking, "Breaking changes"),
+    (Severity::Warning, "Warnings"),
+    (Severity::NonBreaking, "Non-breaking changes"),
+    (Severity::Info, "Info"),
+    (Severity::Unstable, "Unstable changes"),
+];
+
+impl<'a> Info<'a> {
+    fn of(spec: &'a OpenAPI) -> Self {
+        Info {
+            title: &spec.info.title,
+            version: &spec.info.version,
+            description: spec.info.description.as_deref(),
+        }
+    }
+}
+
+fn scope_label(change: &Change) -> String {
+    match change.location.scope() {
+        Location::Document => "Document".into(),
+        scope => scope.to_string(),
+    }
+}
+
+/// Render `diff` through the minijinja template `source`. `name` is used in
+/// error messages and picks auto-escaping: templates named `*.html` or
+/// `*.xml` escape their output. Blocks are trimmed (`trim_blocks` and
+/// `lstrip_blocks`), so tags on lines of their own leave no blank lines.
+pub fn template(
+    name: &str,
+    source: &str,
+    diff: &Diff,
+    old: &OpenAPI,
+    new: &OpenAPI,
+) -> Result<String, minijinja::Error> {
+    let changes: Vec<Entry> = diff
+        .changes()
+        .iter()
+        .map(|change| Entry {
+            change,
+            scope: scope_label(change),
+            detail: change.location.detail(),
+            description: change.describe(),
+            text: change.to_string(),
+            fingerprint: change.fingerprint(),
+        })
+        .collect();
+    let count = |severity| {
+        changes
+            .iter()
+            .filter(|c| c.change.severity == severity)
+            .count()
+    };
+    let counts = Counts {
+        total: changes.len(),
+        breaking: count(Severity::Breaking),
+        warning: count(Severity::Warning),
+        non_breaking: count(Severity::NonBreaking),
+        info: count(Severity::Info),
+        unstable: count(Severity::Unstable),
+    };
+    let by_severity = SECTIONS
+        .iter()
+        .map(|&(severity, title)| SeverityGroup {
+            severity,
+            title,
+            changes: changes
+                .iter()
+                .filter(|c| c.change.severity == severity)
+                .cloned()
+                .collect(),
+        })
+        .filter(|group| !group.changes.is_empty())
+        .collect();
+    let mut by_scope: Vec<ScopeGroup> = Vec::new();
+    for entry in &changes {
+        match by_scope.iter_mut().find(|g| g.scope == entry.scope) {
+            Some(group) => group.changes.push(entry.clone()),
+            None => by_scope.push(ScopeGroup {
+                scope: entry.scope.clone(),
+                changes: vec![entry.clone()],
+            }),
+        }
+    }
+    let context = Context {
+        old: Info::of(old),
+        new: Info::of(new),
+        has_breaking: diff.has_breaking(),
+        changes,
+        counts,
+        by_severity,
+        by_scope,
+    };
+
+    let mut env = Environment::new();
+    env.set_keep_trailing_newline(true);
+    env.set_trim_blocks(true);
+    env.set_lstrip_blocks(true);
+    env.render_named_str(name, source, context)
+}
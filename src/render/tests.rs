@@ -0,0 +1,623 @@
+use super::*;
+use crate::diff::{Event, EventKind, RuleContext, diff_specs, diff_specs_with};
+use crate::{DiffOptions, Rule, Rules, Severity};
+use openapiv3::OpenAPI;
+
+fn spec(limit: &str, status_values: &str) -> OpenAPI {
+    serde_yml::from_str(&format!(
+        r#"
+openapi: "3.0.3"
+info:
+  title: Test
+  version: "1.0.0"
+paths:
+  /pets:
+    get:
+      parameters:
+        - name: limit
+          in: query
+          schema:
+            type: {limit}
+      responses:
+        "200":
+          description: OK
+          content:
+            application/json:
+              schema:
+                type: object
+                properties:
+                  status:
+                    type: string
+                    enum: [{status_values}]
+  /owners:
+    get:
+      responses:
+        "200":
+          description: OK
+"#
+    ))
+    .unwrap()
+}
+
+#[test]
+fn tree_groups_changes_by_structure() {
+    let diff = diff_specs(
+        &spec("integer", "available, sold"),
+        &spec("string", "available, sold, pending"),
+    );
+    let root = diff.tree();
+    assert_eq!(root.severity, Some(crate::change::Severity::Breaking));
+    assert_eq!(root.children.len(), 1);
+    assert_eq!(root.children[0].label, "/pets");
+
+    let expected = "\
+/pets GET [breaking]
+├── parameters query parameter 'limit' [breaking]
+│   └── [breaking] type changed from integer to string
+└── responses '200' application/json status [breaking]
+    └── [breaking] enum value 'pending' added
+";
+    assert_eq!(tree(&root), expected);
+}
+
+#[test]
+fn tree_nests_additions_and_schema_steps() {
+    let spec = |yaml: &str| -> OpenAPI {
+        serde_yml::from_str(&format!(
+            "openapi: \"3.0.3\"\ninfo:\n  title: Test\n  version: \"1.0.0\"\n{yaml}"
+        ))
+        .unwrap()
+    };
+    let old = spec(
+        r#"
+paths:
+  /pets:
+    get:
+      responses:
+        "200":
+          description: OK
+          content:
+            application/json:
+              schema:
+                type: array
+                items:
+                  type: object
+                  properties:
+                    status:
+                      type: string
+                      enum: [available]
+"#,
+    );
+    let new = spec(
+        r#"
+paths:
+  /pets:
+    get:
+      parameters:
+        - name: species
+          in: query
+          required: true
+          schema:
+            type: string
+      responses:
+        "200":
+          description: OK
+          content:
+            application/json:
+              schema:
+                type: array
+                items:
+                  type: object
+                  properties:
+                    status:
+                      type: string
+                      enum: [available, sold]
+        "404":
+          description: Not found
+"#,
+    );
+    let expected = "\
+/pets GET [breaking]
+├── parameters query parameter 'species' [breaking]
+│   └── [breaking] added
+└── responses [breaking]
+    ├── '200' application/json [] status [breaking]
+    │   └── [breaking] enum value 'sold' added
+    └── '404' [non-breaking]
+        └── [non-breaking] added
+";
+    assert_eq!(tree(&diff_specs(&old, &new).tree()), expected);
+}
+
+#[test]
+fn text_lists_sections_by_severity() {
+    let diff = diff_specs(
+        &spec("integer", "available"),
+        &spec("integer", "available, sold"),
+    );
+    assert_eq!(
+        text(&diff),
+        "Breaking changes (1):\n  GET /pets - response '200' application/json.status: enum value 'sold' added\n"
+    );
+}
+
+#[test]
+fn text_names_components_without_usages() {
+    let spec = |deprecated: bool| -> OpenAPI {
+        serde_yml::from_str(&format!(
+            r#"
+openapi: "3.0.3"
+info:
+  title: Test
+  version: "1.0.0"
+paths: {{}}
+components:
+  schemas:
+    Owner:
+      type: object
+      deprecated: {deprecated}
+"#
+        ))
+        .unwrap()
+    };
+    let diff = diff_specs(&spec(false), &spec(true));
+    assert_eq!(
+        text(&diff),
+        "Non-breaking changes (1):\n  component Owner:\n    schema deprecated\n"
+    );
+}
+
+#[test]
+fn html_is_a_self_contained_report() {
+    let old = spec("integer", "available");
+    let new = spec("string", "available, <sold>");
+    let report = html(&diff_specs(&old, &new), &old, &new);
+
+    assert!(report.starts_with("<!DOCTYPE html>"));
+    assert!(!report.contains("src=") && !report.contains("href="));
+    assert!(
+        report.contains("<div class=\"card breaking\"><div class=\"count\">2</div>breaking</div>")
+    );
+    assert!(
+        report.contains("<details class=\"endpoint\" open data-path=\"GET /pets\" data-tags=\"\">")
+    );
+    assert!(report.contains(
+        "<div class=\"change\" data-severity=\"breaking\"><span class=\"badge breaking\">breaking</span>query parameter &#39;limit&#39;: type changed from integer to string"
+    ));
+    // Each change shows its location in both specs, escaped.
+    assert!(report.contains("&quot;type&quot;: &quot;integer&quot;"));
+    assert!(report.contains("&quot;&lt;sold&gt;&quot;"));
+    assert!(!report.contains("<sold>"));
+}
+
+#[test]
+fn html_filters_by_tags_of_affected_operations() {
+    let old = spec("integer", "available");
+    let mut new = spec("integer", "available, sold");
+    let mut tagged = new.paths.paths["/pets"].as_item().cloned().unwrap();
+    tagged.get.as_mut().unwrap().tags = vec!["pets".into(), "store".into()];
+    new.paths.paths["/pets"] = openapiv3::ReferenceOr::Item(tagged);
+
+    let report = html(&diff_specs(&old, &new), &old, &new);
+    assert!(report.contains("data-tags=\"pets|store\""));
+    assert!(
+        report
+            .contains("<option value=\"pets\">pets</option><option value=\"store\">store</option>")
+    );
+
+    let unchanged = html(&diff_specs(&old, &old), &old, &old);
+    assert!(unchanged.contains("No changes detected."));
+    assert!(!unchanged.contains("<script>"));
+}
+
+#[test]
+fn junit_has_a_test_case_per_operation() {
+    let old = spec("integer", "available");
+    let new = spec("string", "available, sold");
+    let report = junit(&diff_specs(&old, &new), "old.yaml -> new.yaml", &old, &new);
+    assert_eq!(
+        report,
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="apidiff" tests="2" failures="1" errors="0">
+  <testsuite name="old.yaml -&gt; new.yaml" tests="2" failures="1" errors="0" skipped="0">
+    <testcase name="GET /pets" classname="old.yaml -&gt; new.yaml">
+      <failure message="2 breaking changes" type="breaking">query parameter &#39;limit&#39;: type changed from integer to string
+response &#39;200&#39; application/json.status: enum value &#39;sold&#39; added</failure>
+    </testcase>
+    <testcase name="GET /owners" classname="old.yaml -&gt; new.yaml"/>
+  </testsuite>
+</testsuites>
+"#
+    );
+}
+
+#[test]
+fn junit_reports_other_changes_as_output() {
+    let old = spec("integer", "available");
+    let mut new = old.clone();
+    let owners = new.paths.paths["/owners"].clone();
+    new.paths.paths.insert("/toys".into(), owners);
+    let report = junit(&diff_specs(&old, &new), "specs", &old, &new);
+    assert!(report.contains(r#"tests="3" failures="0""#));
+    assert!(report.contains(
+        "<testcase name=\"GET /toys\" classname=\"specs\">\n      <system-out>[non-breaking] endpoint added</system-out>"
+    ));
+}
+
+#[test]
+fn changelog_lists_releases_newest_first() {
+    let v1 = spec("integer", "available");
+    let mut v2 = spec("integer", "available");
+    v2.info.version = "1.1.0".into();
+    let owners = v2.paths.paths["/owners"].clone();
+    v2.paths.paths.insert("/toys".into(), owners);
+    v2.paths.paths.shift_remove("/owners");
+    if let openapiv3::ReferenceOr::Item(pets) = &mut v2.paths.paths["/pets"] {
+        pets.get.as_mut().unwrap().deprecated = true;
+    }
+    let mut v3 = spec("string", "available");
+    v3.info.version = "2.0.0".into();
+    v3.paths = v2.paths.clone();
+    v3.paths.paths["/pets"] = spec("string", "available").paths.paths["/pets"].clone();
+
+    let first = diff_specs(&v1, &v2);
+    let second = diff_specs(&v2, &v3);
+    let unchanged = diff_specs(&v3, &v3);
+    assert_eq!(
+        changelog(&[("1.1.0", &first), ("2.0.0", &second), ("2.0.1", &unchanged)]),
+        "\
+# Changelog
+
+All notable changes to this API are documented in this file.
+
+The format is based on [Keep a Changelog](https://keepachangelog.com/en/1.1.0/).
+
+## [2.0.1]
+
+No API changes.
+
+## [2.0.0]
+
+### Changed
+
+- `GET /pets`: operation no longer deprecated
+
+### Breaking
+
+- `GET /pets`: query parameter 'limit': type changed from integer to string
+
+## [1.1.0]
+
+### Added
+
+- `/toys`: endpoint added
+
+### Deprecated
+
+- `GET /pets`: operation deprecated
+
+### Breaking
+
+- `/owners`: endpoint removed
+"
+    );
+}
+
+/// Lets endpoints go without a breaking change, as after a deprecation.
+struct Retired;
+
+impl Rule for Retired {
+    fn name(&self) -> &str {
+        "retired"
+    }
+
+    fn classify(&self, event: &Event, _: &RuleContext) -> Option<Severity> {
+        (event.kind == EventKind::PathRemoved).then_some(Severity::NonBreaking)
+    }
+}
+
+#[test]
+fn changelog_sections_follow_the_event_not_the_wording() {
+    let old = spec("integer", "available");
+    let mut new = spec("integer", "available");
+    new.paths.paths.shift_remove("/owners");
+    let options = DiffOptions::new().rules(Rules::default().with(Retired));
+    let diff = diff_specs_with(&old, &new, &options);
+
+    // Reword the change, and add a finding of a rule, which has no event.
+    let mut json = serde_json::to_value(&diff).unwrap();
+    json["changes"][0]["message"] = "endpoint dropped".into();
+    let mut finding = json["changes"][0].clone();
+    finding["message"] = "endpoint added to the deny list".into();
+    finding.as_object_mut().unwrap().remove("event");
+    json["changes"].as_array_mut().unwrap().push(finding);
+    let diff: Diff = serde_json::from_value(json).unwrap();
+
+    let log = changelog(&[("1.1.0", &diff)]);
+    assert!(log.contains("### Changed\n\n- `/owners`: endpoint added to the deny list\n"));
+    assert!(log.contains("### Removed\n\n- `/owners`: endpoint dropped\n"));
+}
+
+fn tenant_spec(header: &str, kinds: &str, required: &str) -> OpenAPI {
+    serde_yml::from_str(&format!(
+        r#"
+openapi: "3.0.3"
+info:
+  title: Pets
+  version: "1.0.0"
+paths:
+  /pets:
+    get:
+      parameters:{header}
+      responses:
+        "200":
+          description: OK
+          content:
+            application/json:
+              schema:
+                type: object
+                properties:
+                  kind:
+                    type: string
+                    enum: [{kinds}]
+    post:
+      requestBody:
+        content:
+          application/json:
+            schema:
+              type: object
+              required: [{required}]
+              properties:
+                species:
+                  type: string
+                  example: cat
+      responses:
+        "201":
+          description: Created
+"#
+    ))
+    .unwrap()
+}
+
+const TENANT: &str = "
+        - name: X-Tenant
+          in: header
+          required: true
+          example: acme
+          schema:
+            type: string";
+
+#[test]
+fn migration_turns_breaking_changes_into_instructions() {
+    let old = tenant_spec(" []", "cat, dog", "");
+    let mut new = tenant_spec(TENANT, "cat, dog, reptile", "species");
+    new.info.version = "2.0.0".into();
+    let diff = diff_specs(&old, &new);
+    assert!(diff.changes().iter().all(|c| c.event.is_some()));
+
+    assert_eq!(
+        migration(&diff, &old, &new),
+        "\
+# Migrating from Pets 1.0.0 to 2.0.0
+
+## GET /pets
+
+- Send the new required header `X-Tenant`. For example: `acme`.
+- Handle the new enum value `reptile` in `kind` in the `200` response.
+
+## POST /pets
+
+- Always send `species` in the request body. For example: `cat`.
+"
+    );
+
+    let none = diff_specs(&old, &old);
+    assert!(migration(&none, &old, &old).ends_with("existing clients need no changes.\n"));
+}
+
+/// Judges every addition breaking, as a strict policy might.
+struct NoAdditions;
+
+impl Rule for NoAdditions {
+    fn name(&self) -> &str {
+        "no-additions"
+    }
+
+    fn classify(&self, event: &Event, _: &RuleContext) -> Option<Severity> {
+        matches!(
+            event.kind,
+            EventKind::ParameterAdded { .. } | EventKind::PropertyAdded { .. }
+        )
+        .then_some(Severity::Breaking)
+    }
+}
+
+#[test]
+fn migration_words_additions_by_whether_they_are_required() {
+    let optional = TENANT.replace("required: true", "required: false");
+    let old = tenant_spec(" []", "cat", "");
+    let new = tenant_spec(&optional, "cat", "");
+    let mut new = serde_json::to_value(&new).unwrap();
+    new["paths"]["/pets"]["post"]["requestBody"]["content"]["application/json"]["schema"]["properties"]
+        ["name"] = serde_json::json!({ "type": "string" });
+    let new: OpenAPI = serde_json::from_value(new).unwrap();
+    let options = DiffOptions::new().rules(Rules::default().with(NoAdditions));
+    let diff = diff_specs_with(&old, &new, &options);
+
+    let guide = migration(&diff, &old, &new);
+    assert!(
+        guide.contains(
+            "- Consider sending the new optional header `X-Tenant`. For example: `acme`.\n"
+        )
+    );
+    assert!(
+        guide
+            .contains("- Consider sending the new optional property `name` in the request body.\n")
+    );
+}
+
+fn shared_pet_spec(properties: &str) -> OpenAPI {
+    serde_yml::from_str(&format!(
+        r##"
+openapi: "3.0.3"
+info:
+  title: Pets
+  version: "1.0.0"
+paths:
+  /pets:
+    get:
+      responses:
+        "200":
+          description: OK
+          content:
+            application/json:
+              schema:
+                $ref: "#/components/schemas/Pet"
+    post:
+      requestBody:
+        content:
+          application/json:
+            schema:
+              $ref: "#/components/schemas/Pet"
+      responses:
+        "201":
+          description: Created
+components:
+  schemas:
+    Pet:
+      type: object
+      properties:
+        name:
+          type: string{properties}
+"##
+    ))
+    .unwrap()
+}
+
+#[test]
+fn migration_follows_the_breaking_direction_of_grouped_components() {
+    let old = shared_pet_spec(
+        "
+        species:
+          type: string",
+    );
+    let new = shared_pet_spec("");
+    let options = DiffOptions::new().group_components(true);
+    let diff = diff_specs_with(&old, &new, &options);
+
+    assert!(diff[0].message.contains("breaking because"));
+    assert_eq!(
+        migration(&diff, &old, &new),
+        "\
+# Migrating from Pets 1.0.0 to 1.0.0
+
+## component Pet
+
+- Stop reading `species` in the `Pet` schema: it is no longer returned.
+"
+    );
+}
+
+#[test]
+fn templates_see_changes_groups_counts_and_spec_info() {
+    let old = spec("integer", "available");
+    let mut new = spec("string", "available, <sold>");
+    new.info.version = "2.0.0".into();
+    let diff = diff_specs(&old, &new);
+    let source = "\
+{{ new.title }} {{ old.version }} -> {{ new.version }}: {{ counts.total }} changes, {{ counts.breaking }} breaking
+{% for group in by_scope %}
+{{ group.scope }}
+{% for c in group.changes %}
+  [{{ c.severity }}] {{ c.description }} ({{ c.fingerprint|length }})
+{% endfor %}
+{% endfor %}
+{{ by_severity|map(attribute='title')|join(', ') }}
+";
+    assert_eq!(
+        template("report.txt", source, &diff, &old, &new).unwrap(),
+        "\
+Test 1.0.0 -> 2.0.0: 2 changes, 2 breaking
+GET /pets
+  [breaking] query parameter 'limit': type changed from integer to string (16)
+  [breaking] response '200' application/json.status: enum value '<sold>' added (16)
+Breaking changes
+"
+    );
+
+    let html = template("report.html", "{{ changes[1].message }}", &diff, &old, &new);
+    assert_eq!(html.unwrap(), "enum value &#x27;&lt;sold&gt;&#x27; added");
+
+    let err = template("bad.txt", "{% for %}", &diff, &old, &new).unwrap_err();
+    assert!(err.to_string().contains("bad.txt:1"));
+}
+
+#[test]
+fn locations_find_their_node_in_a_spec() {
+    let spec = serde_json::json!({
+        "paths": {
+            "/pets": {
+                "parameters": [{ "$ref": "#/components/parameters/Limit" }],
+                "get": {
+                    "security": [{ "oauth": ["read"] }, { "key": [] }],
+                    "responses": {
+                        "200": {
+                            "headers": { "X-Rate-Limit": { "schema": { "type": "integer" } } },
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "type": "array",
+                                        "items": { "$ref": "#/components/schemas/Pet" }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        },
+        "components": {
+            "parameters": {
+                "Limit": { "name": "limit", "in": "query", "required": true }
+            },
+            "schemas": {
+                "Pet": {
+                    "allOf": [{
+                        "properties": {
+                            "owner": { "properties": { "name": { "type": "string" } } }
+                        }
+                    }]
+                }
+            }
+        }
+    });
+    let op = Location::Operation {
+        path: "/pets".into(),
+        method: "GET".into(),
+    };
+    let find = |loc: Location| node::find(&loc, &spec).unwrap();
+
+    assert_eq!(find(op.parameter("limit", "query"))["required"], true);
+    let response = op.response("200");
+    assert_eq!(
+        find(response.header("X-Rate-Limit"))["schema"]["type"],
+        "integer"
+    );
+    let media = response.media_type("application/json");
+    assert_eq!(
+        find(media.items().property("owner").property("name")),
+        serde_json::json!({ "type": "string" })
+    );
+    assert_eq!(
+        find(op.security("oauth")),
+        serde_json::json!([{ "oauth": ["read"] }])
+    );
+    let served = Location::MediaType {
+        parent: Box::new(response.clone()),
+        media: "application/*".into(),
+        new_media: Some("application/json".into()),
+    };
+    assert_eq!(find(served)["schema"]["type"], "array");
+    assert_eq!(node::find(&op.parameter("offset", "query"), &spec), None);
+    assert_eq!(node::find(&media.property("missing"), &spec), None);
+}
@@ -0,0 +1,97 @@
+//! Reading back diffs saved as JSON.
+
+use openapiv3::OpenAPI;
+use serde_json::Value;
+use std::fmt;
+use std::path::Path;
+
+use crate::diff::Diff;
+use crate::plugin::PROTOCOL_VERSION;
+
+/// A saved diff, with the specs it was made from when they were saved too.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct Report {
+    /// The old spec.
+    pub old: Option<OpenAPI>,
+    /// The new spec.
+    pub new: Option<OpenAPI>,
+    /// The changes between them.
+    pub diff: Diff,
+}
+
+/// A saved report that could not be read.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ReportError {
+    /// The file could not be read.
+    Io(String, std::io::Error),
+    /// The file is not a saved diff.
+    Parse(String, serde_json::Error),
+    /// The file was written with a newer protocol version.
+    Version(String, u64),
+}
+
+impl fmt::Display for ReportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReportError::Io(path, e) => write!(f, "failed to read '{path}': {e}"),
+            ReportError::Parse(path, e) => write!(f, "invalid report '{path}': {e}"),
+            ReportError::Version(path, version) => write!(
+                f,
+                "report '{path}' has version {version}, but only versions up to {PROTOCOL_VERSION} are supported"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ReportError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ReportError::Io(_, e) => Some(e),
+            ReportError::Parse(_, e) => Some(e),
+            ReportError::Version(..) => None,
+        }
+    }
+}
+
+impl Report {
+    /// Read a report from a file.
+    pub fn load(path: &Path) -> Result<Self, ReportError> {
+        let name = path.display().to_string();
+        let json = std::fs::read_to_string(path).map_err(|e| ReportError::Io(name.clone(), e))?;
+        Report::parse(&json, &name)
+    }
+
+    /// Parse a report: either the document `--format json` writes and
+    /// plugins receive, or a bare serialized [`Diff`]. `name` is used in
+    /// error messages.
+    pub fn parse(json: &str, name: &str) -> Result<Self, ReportError> {
+        let parse_error = |e| ReportError::Parse(name.into(), e);
+        let mut value: Value = serde_json::from_str(json).map_err(parse_error)?;
+        let Some(diff) = value.get_mut("diff").map(Value::take) else {
+            return Ok(Report {
+                old: None,
+                new: None,
+                diff: serde_json::from_value(value).map_err(parse_error)?,
+            });
+        };
+        if let Some(version) = value.get("version").and_then(Value::as_u64)
+            && version > u64::from(PROTOCOL_VERSION)
+        {
+            return Err(ReportError::Version(name.into(), version));
+        }
+        let mut spec = |key: &str| match value.get_mut(key).map(Value::take) {
+            Some(spec) => serde_json::from_value(spec).map(Some),
+            None => Ok(None),
+        };
+        Ok(Report {
+            old: spec("old").map_err(parse_error)?,
+            new: spec("new").map_err(parse_error)?,
+            diff: serde_json::from_value(diff).map_err(parse_error)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests;
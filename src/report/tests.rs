@@ -0,0 +1,134 @@
+use super::*;
+use crate::diff::{diff_specs, diff_specs_with};
+use crate::plugin::Input;
+use crate::{DiffOptions, Location, RemovalPolicy, Severity};
+
+fn spec(limit: &str, version: &str) -> OpenAPI {
+    serde_yml::from_str(&format!(
+        r#"
+openapi: "3.0.3"
+info:
+  title: Test
+  version: "{version}"
+paths:
+  /pets:
+    get:
+      parameters:
+        - name: limit
+          in: query
+          schema:
+            type: {limit}
+      responses:
+        "200":
+          description: OK
+"#
+    ))
+    .unwrap()
+}
+
+#[test]
+fn saved_reports_round_trip() {
+    let (old, new) = (spec("integer", "1.0.0"), spec("string", "2.0.0"));
+    let diff = diff_specs(&old, &new);
+    let json = serde_json::to_string(&Input::new(&old, &new, &diff)).unwrap();
+
+    let report = Report::parse(&json, "diff.json").unwrap();
+    assert_eq!(report.new.unwrap().info.version, "2.0.0");
+    assert_eq!(report.old.unwrap().info.version, "1.0.0");
+    let change = &report.diff[0];
+    assert_eq!(change.severity, Severity::Breaking);
+    assert_eq!(
+        change.location,
+        Location::Operation {
+            path: "/pets".into(),
+            method: "GET".into(),
+        }
+        .parameter("limit", "query")
+    );
+    assert_eq!(change.fingerprint(), diff[0].fingerprint());
+    assert_eq!(change.event, diff[0].event);
+    assert!(change.event.is_some());
+
+    let bare = Report::parse(&serde_json::to_string(&diff).unwrap(), "diff.json").unwrap();
+    assert!(bare.old.is_none() && bare.new.is_none());
+    assert_eq!(bare.diff.changes()[0].to_string(), diff[0].to_string());
+    assert_eq!(bare.diff[0].event, diff[0].event);
+
+    let resaved = serde_json::to_value(Input::without_specs(&bare.diff)).unwrap();
+    assert_eq!(resaved["version"], 1);
+    assert!(resaved.get("old").is_none() && resaved.get("new").is_none());
+    assert_eq!(
+        resaved["diff"]["changes"][0]["fingerprint"],
+        diff[0].fingerprint()
+    );
+}
+
+#[test]
+fn newer_or_invalid_reports_are_rejected() {
+    let err = Report::parse(r#"{"version": 2, "diff": {}}"#, "new.json").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "report 'new.json' has version 2, but only versions up to 1 are supported"
+    );
+    let err = Report::parse(r#"{"changes": 3}"#, "bad.json").unwrap_err();
+    assert!(matches!(err, ReportError::Parse(name, _) if name == "bad.json"));
+}
+
+#[test]
+fn reports_can_be_compared() {
+    let first = diff_specs(&spec("integer", "1.0.0"), &spec("string", "1.0.0"));
+    let second = diff_specs(&spec("integer", "1.0.0"), &spec("boolean", "1.0.0"));
+    let messages =
+        |diff: &Diff| -> Vec<String> { diff.changes().iter().map(|c| c.message.clone()).collect() };
+
+    assert_eq!(
+        messages(&second.since(&first)),
+        ["type changed from integer to boolean"]
+    );
+    assert_eq!(
+        messages(&first.since(&second)),
+        ["type changed from integer to string"]
+    );
+    assert!(first.since(&first).is_empty());
+}
+
+#[test]
+fn reports_made_with_other_options_match_by_fingerprint() {
+    let spec = |parameters: &str| -> OpenAPI {
+        serde_yml::from_str(&format!(
+            r#"
+openapi: "3.0.3"
+info:
+  title: Test
+  version: "1.0.0"
+paths:
+  /pets:
+    get:
+      parameters:{parameters}
+      responses:
+        "200":
+          description: OK
+"#
+        ))
+        .unwrap()
+    };
+    let old = spec(
+        "
+        - name: legacy
+          in: query
+          required: true
+          deprecated: true
+          schema:
+            type: string",
+    );
+    let new = spec(" []");
+    let strict = diff_specs(&old, &new);
+    let options = DiffOptions::new().removal_policy(RemovalPolicy::AllowDeprecated);
+    let allowed = diff_specs_with(&old, &new, &options);
+
+    let rejudged = allowed.since(&strict);
+    assert_eq!(rejudged.len(), 1);
+    assert_eq!(rejudged[0].severity, Severity::NonBreaking);
+    assert!(allowed.resolved_since(&strict).is_empty());
+    assert!(allowed.since(&allowed).is_empty());
+}
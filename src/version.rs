@@ -0,0 +1,129 @@
+//! Checking `info.version` bumps against the changes in a diff.
+
+use crate::diff::Diff;
+use semver::Version;
+use std::fmt;
+
+/// A semantic version increment, ordered from smallest to largest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Bump {
+    /// `x.y.Z`
+    Patch,
+    /// `x.Y.0`
+    Minor,
+    /// `X.0.0`
+    Major,
+}
+
+impl fmt::Display for Bump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Bump::Patch => write!(f, "patch"),
+            Bump::Minor => write!(f, "minor"),
+            Bump::Major => write!(f, "major"),
+        }
+    }
+}
+
+/// A mismatch between the changes in a diff and the `info.version` bump.
+#[derive(Debug, PartialEq)]
+#[non_exhaustive]
+pub enum Finding {
+    /// Breaking changes shipped without the bump they require.
+    Error(String),
+    /// The bump doesn't match the changes, but nothing breaks for clients.
+    Warning(String),
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Finding::Error(msg) => write!(f, "error: {msg}"),
+            Finding::Warning(msg) => write!(f, "warning: {msg}"),
+        }
+    }
+}
+
+/// Parse an `info.version` value as a semantic version.
+pub fn parse(version: &str) -> Result<Version, String> {
+    Version::parse(version)
+        .map_err(|e| format!("info.version '{version}' is not a semantic version: {e}"))
+}
+
+/// The bump the changes in `diff` call for, starting from `old`.
+///
+/// Breaking changes need a major bump, other changes a minor one, and
+/// documentation-only changes or changes limited to unstable operations a
+/// patch. Below 1.0.0 everything shifts down one level, since the minor
+/// version acts as the major one.
+pub fn required_bump(diff: &Diff, old: &Version) -> Option<Bump> {
+    let bump = if diff.has_breaking() {
+        Bump::Major
+    } else if !diff.warnings().is_empty() || !diff.non_breaking().is_empty() {
+        Bump::Minor
+    } else if !diff.is_empty() {
+        Bump::Patch
+    } else {
+        return None;
+    };
+    if old.major > 0 {
+        return Some(bump);
+    }
+    match bump {
+        Bump::Major => Some(Bump::Minor),
+        Bump::Minor | Bump::Patch => Some(Bump::Patch),
+    }
+}
+
+/// The bump between `old` and `new`, or `None` if `new` isn't newer.
+/// Pre-release and build metadata are ignored.
+pub fn actual_bump(old: &Version, new: &Version) -> Option<Bump> {
+    let old_core = (old.major, old.minor, old.patch);
+    let new_core = (new.major, new.minor, new.patch);
+    if new_core <= old_core {
+        None
+    } else if new.major > old.major {
+        Some(Bump::Major)
+    } else if new.minor > old.minor {
+        Some(Bump::Minor)
+    } else {
+        Some(Bump::Patch)
+    }
+}
+
+/// The next version after `old` for the given bump.
+pub fn next_version(old: &Version, bump: Option<Bump>) -> Version {
+    match bump {
+        None => old.clone(),
+        Some(Bump::Major) => Version::new(old.major + 1, 0, 0),
+        Some(Bump::Minor) => Version::new(old.major, old.minor + 1, 0),
+        Some(Bump::Patch) => Version::new(old.major, old.minor, old.patch + 1),
+    }
+}
+
+/// Compare the `info.version` bump from `old` to `new` against `diff`.
+pub fn check(old: &Version, new: &Version, diff: &Diff) -> Option<Finding> {
+    let required = required_bump(diff, old)?;
+    let actual = actual_bump(old, new);
+    if actual == Some(required) {
+        return None;
+    }
+
+    let went = if old == new {
+        format!("info.version stayed at {old}")
+    } else {
+        format!("info.version went from {old} to {new}")
+    };
+    if diff.has_breaking() && actual < Some(required) {
+        Some(Finding::Error(format!(
+            "breaking changes require a {required} version bump, but {went}"
+        )))
+    } else {
+        Some(Finding::Warning(format!(
+            "changes call for a {required} version bump, but {went}"
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,95 @@
+use super::*;
+use crate::diff::diff_specs;
+use openapiv3::OpenAPI;
+
+fn spec(extra_param: Option<bool>) -> OpenAPI {
+    let param = match extra_param {
+        Some(required) => format!(
+            r#"
+      parameters:
+        - name: limit
+          in: query
+          required: {required}
+          schema:
+            type: integer"#
+        ),
+        None => String::new(),
+    };
+    serde_yml::from_str(&format!(
+        r#"
+openapi: "3.0.3"
+info:
+  title: Test
+  version: "1.0.0"
+paths:
+  /pets:
+    get:{param}
+      responses:
+        "200":
+          description: OK
+"#
+    ))
+    .unwrap()
+}
+
+fn v(version: &str) -> Version {
+    parse(version).unwrap()
+}
+
+#[test]
+fn breaking_change_without_major_bump_is_an_error() {
+    let diff = diff_specs(&spec(None), &spec(Some(true)));
+    let finding = check(&v("1.2.0"), &v("1.3.0"), &diff);
+    assert_eq!(
+        finding,
+        Some(Finding::Error(
+            "breaking changes require a major version bump, but info.version went from 1.2.0 to 1.3.0"
+                .into()
+        ))
+    );
+    assert_eq!(check(&v("1.2.0"), &v("2.0.0"), &diff), None);
+}
+
+#[test]
+fn minor_and_patch_mismatches_are_warnings() {
+    let diff = diff_specs(&spec(None), &spec(Some(false)));
+    assert_eq!(check(&v("1.2.0"), &v("1.3.0"), &diff), None);
+    assert!(matches!(
+        check(&v("1.2.0"), &v("1.2.1"), &diff),
+        Some(Finding::Warning(_))
+    ));
+    assert_eq!(
+        check(&v("1.2.0"), &v("1.2.0"), &diff),
+        Some(Finding::Warning(
+            "changes call for a minor version bump, but info.version stayed at 1.2.0".into()
+        ))
+    );
+}
+
+#[test]
+fn no_changes_need_no_bump() {
+    let diff = diff_specs(&spec(None), &spec(None));
+    assert_eq!(required_bump(&diff, &v("1.0.0")), None);
+    assert_eq!(check(&v("1.0.0"), &v("1.0.0"), &diff), None);
+}
+
+#[test]
+fn pre_1_0_versions_shift_down_one_level() {
+    let diff = diff_specs(&spec(None), &spec(Some(true)));
+    assert_eq!(required_bump(&diff, &v("0.4.2")), Some(Bump::Minor));
+    assert_eq!(check(&v("0.4.2"), &v("0.5.0"), &diff), None);
+}
+
+#[test]
+fn suggests_next_version() {
+    let old = v("1.4.2-rc.1");
+    assert_eq!(next_version(&old, Some(Bump::Major)), v("2.0.0"));
+    assert_eq!(next_version(&old, Some(Bump::Minor)), v("1.5.0"));
+    assert_eq!(next_version(&old, Some(Bump::Patch)), v("1.4.3"));
+    assert_eq!(next_version(&old, None), old);
+}
+
+#[test]
+fn rejects_non_semver_versions() {
+    assert!(parse("v1").unwrap_err().contains("info.version 'v1'"));
+}